@@ -0,0 +1,16 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+mod prechecker_actions;
+mod prechecker_effects;
+mod prechecker_manager_operation;
+mod prechecker_reducer;
+mod prechecker_requests;
+mod prechecker_state;
+
+pub use prechecker_actions::*;
+pub use prechecker_effects::*;
+pub use prechecker_manager_operation::*;
+pub use prechecker_reducer::*;
+pub use prechecker_requests::*;
+pub use prechecker_state::*;
@@ -18,8 +18,9 @@ use crate::{
 };
 
 use super::{
-    EndorsementValidationError, Key, OperationDecodedContents, PrecheckerError,
-    PrecheckerResponseError, SupportedProtocolState,
+    prechecker_requests::{PrecheckerQuery, PrecheckerQueryResponse},
+    EndorsementValidationError, Key, ManagerOperationValidationError, OperationDecodedContents,
+    PrecheckerError, PrecheckerResponseError, SupportedProtocolState,
 };
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -44,12 +45,48 @@ impl EnablingCondition<State> for PrecheckerPrecheckOperationResponseAction {
     }
 }
 
+/// Decodes a batch of operations and verifies them without going through the
+/// per-operation action chain, so the signature checks can be parallelized with
+/// rayon (and batched per curve for ed25519) instead of serialized one action at
+/// a time on the state-machine thread. The single-op API is unaffected.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerPrecheckOperationsBatchAction {
+    pub operations: Vec<Operation>,
+}
+
+impl EnablingCondition<State> for PrecheckerPrecheckOperationsBatchAction {
+    fn is_enabled(&self, _state: &State) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerPrecheckOperationsBatchResponseAction {
+    pub responses: Vec<PrecheckerPrecheckOperationResponse>,
+}
+
+impl EnablingCondition<State> for PrecheckerPrecheckOperationsBatchResponseAction {
+    fn is_enabled(&self, _state: &State) -> bool {
+        true
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum PrecheckerPrecheckOperationResponse {
     /// The operation can be applied.
     Applied(PrecheckerApplied),
+    /// The operation is valid in shape but references a branch we have not applied yet,
+    /// or a level ahead of the current head. It may become applicable once the node
+    /// advances and should be retried rather than dropped.
+    BranchDelayed(PrecheckerErrored),
+    /// The operation is valid in shape but was built on a branch other than the current
+    /// one, or at a level below the current head. It should be kept, but not propagated
+    /// on this branch.
+    BranchRefused(PrecheckerErrored),
     /// The operation cannot be applied.
     Refused(PrecheckerErrored),
+    /// The operation is too old to ever apply on any branch and will never be re-evaluated.
+    Outdated(PrecheckerErrored),
     /// Prechecker cannot decide if the operation is correct. Protocol based prevalidator is needed.
     Prevalidate(PrecheckerPrevalidate),
     /// Error occurred while prechecking the operation.
@@ -131,6 +168,51 @@ impl PrecheckerPrecheckOperationResponseAction {
         }
     }
 
+    pub(super) fn branch_delayed(
+        operation_hash: &OperationHash,
+        protocol_data: serde_json::Value,
+        error: String,
+    ) -> Self {
+        let errored = PrecheckerErrored {
+            hash: operation_hash.clone(),
+            error,
+            protocol_data,
+        };
+        Self {
+            response: PrecheckerPrecheckOperationResponse::BranchDelayed(errored),
+        }
+    }
+
+    pub(super) fn branch_refused(
+        operation_hash: &OperationHash,
+        protocol_data: serde_json::Value,
+        error: String,
+    ) -> Self {
+        let errored = PrecheckerErrored {
+            hash: operation_hash.clone(),
+            error,
+            protocol_data,
+        };
+        Self {
+            response: PrecheckerPrecheckOperationResponse::BranchRefused(errored),
+        }
+    }
+
+    pub(super) fn outdated(
+        operation_hash: &OperationHash,
+        protocol_data: serde_json::Value,
+        error: String,
+    ) -> Self {
+        let errored = PrecheckerErrored {
+            hash: operation_hash.clone(),
+            error,
+            protocol_data,
+        };
+        Self {
+            response: PrecheckerPrecheckOperationResponse::Outdated(errored),
+        }
+    }
+
     #[allow(dead_code)]
     pub(super) fn prevalidate(operation_hash: &OperationHash) -> Self {
         Self {
@@ -224,11 +306,108 @@ pub struct PrecheckerEndorsingRightsReadyAction {
     pub endorsing_rights: EndorsingRights,
 }
 
+/// Fetches a minimal context snapshot (balance, counter, revealed public key) for the
+/// source of a manager operation, mirroring the endorsing-rights request flow.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerGetManagerAccountAction {
+    pub key: Key,
+}
+
+impl EnablingCondition<State> for PrecheckerGetManagerAccountAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerManagerAccountReadyAction {
+    pub key: Key,
+    pub account: super::ManagerAccount,
+}
+
+impl EnablingCondition<State> for PrecheckerManagerAccountReadyAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PrecheckerValidateEndorsementAction {
     pub key: Key,
 }
 
+/// Validates a batch of manager operation contents (transaction/reveal/delegation)
+/// against the account snapshot fetched by `PrecheckerGetManagerAccountAction`:
+/// signature, counter sequencing, gas/storage caps, minimal fee and balance.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerValidateManagerOperationAction {
+    pub key: Key,
+}
+
+impl EnablingCondition<State> for PrecheckerValidateManagerOperationAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerManagerOperationValidationAppliedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+}
+
+impl EnablingCondition<State> for PrecheckerManagerOperationValidationAppliedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerManagerOperationValidationRefusedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+    pub error: ManagerOperationValidationError,
+}
+
+impl EnablingCondition<State> for PrecheckerManagerOperationValidationRefusedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerManagerOperationValidationBranchDelayedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+    pub error: ManagerOperationValidationError,
+}
+
+impl EnablingCondition<State> for PrecheckerManagerOperationValidationBranchDelayedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerManagerOperationValidationOutdatedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+    pub error: ManagerOperationValidationError,
+}
+
+impl EnablingCondition<State> for PrecheckerManagerOperationValidationOutdatedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PrecheckerEndorsementValidationAppliedAction {
     pub key: Key,
@@ -242,6 +421,50 @@ pub struct PrecheckerEndorsementValidationRefusedAction {
     pub error: EndorsementValidationError,
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerEndorsementValidationBranchDelayedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+    pub level: Level,
+    /// The block this endorsement was built on, so later reclassification can tell
+    /// whether this specific branch was applied rather than just comparing levels.
+    pub branch: BlockHash,
+    pub error: EndorsementValidationError,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerEndorsementValidationBranchRefusedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+    pub level: Level,
+    /// The block this endorsement was built on, so later reclassification can tell
+    /// whether this specific branch was applied rather than just comparing levels.
+    pub branch: BlockHash,
+    pub error: EndorsementValidationError,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerEndorsementValidationOutdatedAction {
+    pub key: Key,
+    pub protocol_data: serde_json::Value,
+    pub error: EndorsementValidationError,
+}
+
+/// Re-runs classification of the `BranchDelayed` and `BranchRefused` endorsements against
+/// the newly applied head, promoting/demoting them and pruning anything that became
+/// `Outdated`. `BranchDelayed` manager operations, `Refused` and `Outdated` operations
+/// are not re-evaluated.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerReclassifyOperationsAction {
+    pub level: Level,
+}
+
+impl EnablingCondition<State> for PrecheckerReclassifyOperationsAction {
+    fn is_enabled(&self, _state: &State) -> bool {
+        true
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PrecheckerProtocolNeededAction {
     pub key: Key,
@@ -362,6 +585,7 @@ impl EnablingCondition<State> for PrecheckerPruneOperationAction {
             Some(op) => match op.state {
                 PrecheckerOperationState::Applied { .. }
                 | PrecheckerOperationState::Refused { .. }
+                | PrecheckerOperationState::Outdated { .. }
                 | PrecheckerOperationState::ProtocolNeeded => true,
                 _ => false,
             },
@@ -370,6 +594,30 @@ impl EnablingCondition<State> for PrecheckerPruneOperationAction {
     }
 }
 
+/// Entry point for the monitoring/RPC surface: answers one of the typed
+/// `PrecheckerQuery` variants from the live `state.prechecker.operations` map.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerQueryAction {
+    pub query: PrecheckerQuery,
+}
+
+impl EnablingCondition<State> for PrecheckerQueryAction {
+    fn is_enabled(&self, _state: &State) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerQueryResponseAction {
+    pub response: PrecheckerQueryResponse,
+}
+
+impl EnablingCondition<State> for PrecheckerQueryResponseAction {
+    fn is_enabled(&self, _state: &State) -> bool {
+        true
+    }
+}
+
 impl EnablingCondition<State> for PrecheckerPrecheckOperationInitAction {
     fn is_enabled(&self, state: &State) -> bool {
         let _ = state;
@@ -430,6 +678,24 @@ impl EnablingCondition<State> for PrecheckerEndorsementValidationRefusedAction {
         true
     }
 }
+impl EnablingCondition<State> for PrecheckerEndorsementValidationBranchDelayedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+impl EnablingCondition<State> for PrecheckerEndorsementValidationBranchRefusedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
+impl EnablingCondition<State> for PrecheckerEndorsementValidationOutdatedAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        let _ = state;
+        true
+    }
+}
 impl EnablingCondition<State> for PrecheckerProtocolNeededAction {
     fn is_enabled(&self, state: &State) -> bool {
         let _ = state;
@@ -0,0 +1,924 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+
+use crypto::hash::{BlockHash, OperationHash};
+use tezos_messages::p2p::{
+    binary_message::MessageHash,
+    encoding::{block_header::Level, operation::Operation},
+};
+
+use super::{
+    branch_is_applied, classify_endorsement_level, classify_manager_operation_error,
+    decode_manager_operation_contents, manager_operation_validation_outcome, validate_manager_operation_batch,
+    EndorsementClass, EndorsementValidationError, Key, ManagerAccount, ManagerOperationClass,
+    ManagerOperationOutcome, ManagerOperationValidationError, OperationDecodedContents, PrecheckerApplied,
+    PrecheckerCacheAppliedBlockAction, PrecheckerEndorsementValidationAppliedAction,
+    PrecheckerEndorsementValidationBranchDelayedAction, PrecheckerEndorsementValidationBranchRefusedAction,
+    PrecheckerEndorsementValidationOutdatedAction, PrecheckerEndorsementValidationRefusedAction,
+    PrecheckerErrored, PrecheckerManagerOperationValidationRefusedAction, PrecheckerMempool,
+    PrecheckerMempoolOperation, PrecheckerOperationState, PrecheckerPrecheckOperationResponse,
+    PrecheckerPrecheckOperationsBatchAction, PrecheckerPrecheckOperationsBatchResponseAction,
+    PrecheckerPrevalidate, PrecheckerQuery, PrecheckerQueryResponse, PrecheckerReclassifyOperationsAction,
+    PrecheckerState, PrecheckerStats, PrecheckerValidateEndorsementAction,
+    PrecheckerValidateManagerOperationAction,
+};
+
+/// Builds the follow-up action dispatched whenever a new block is cached as applied:
+/// the reclassification pass over `BranchDelayed`/`BranchRefused` operations keys off
+/// the level of that block.
+pub fn prechecker_reclassify_on_cache_applied_block(
+    action: &PrecheckerCacheAppliedBlockAction,
+) -> PrecheckerReclassifyOperationsAction {
+    reclassify_action_for_level(action.block_header.level())
+}
+
+fn reclassify_action_for_level(level: Level) -> PrecheckerReclassifyOperationsAction {
+    PrecheckerReclassifyOperationsAction { level }
+}
+
+/// A forged operation's signed bytes are everything but the 64-byte signature Tezos
+/// appends at the end of the operation's binary payload.
+fn split_forged_bytes_and_signature(data: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    const SIGNATURE_LEN: usize = 64;
+    if data.len() < SIGNATURE_LEN {
+        return None;
+    }
+    let (message, signature) = data.split_at(data.len() - SIGNATURE_LEN);
+    Some((message.to_vec(), signature.to_vec()))
+}
+
+/// The pieces of a decoded endorsement needed to verify and classify it: the branch it
+/// was built on, the level of that branch, and the slot its endorsing right was
+/// granted for (used to look up the public key to verify its signature against).
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct DecodedEndorsement {
+    pub(crate) branch: BlockHash,
+    pub(crate) level: Level,
+    pub(crate) slot: u16,
+}
+
+/// Parses `branch`/`level`/`slot` out of an endorsement's decoded JSON contents, shaped
+/// like `{"branch": "...", "contents": [{"kind": "endorsement", "level": ..., "slot": ...}]}`.
+/// Returns `None` if any of them is missing or of the wrong type - the caller treats
+/// that as a malformed operation, not as "not ready yet". Also reused by
+/// `prechecker_reduce_precheck_operations_batch_response` to recover the level/branch a
+/// batch-classified endorsement was built on, since `PrecheckerErrored` only carries the
+/// already-rendered `protocol_data`/`error`, not those fields individually.
+pub(crate) fn decode_endorsement(contents: &OperationDecodedContents) -> Option<DecodedEndorsement> {
+    let branch = contents.contents.get("branch")?.as_str()?;
+    let branch = BlockHash::from_base58_check(branch).ok()?;
+    let content = contents.contents.get("contents")?.as_array()?.first()?;
+    let level = content.get("level")?.as_i64()?;
+    let slot = content.get("slot")?.as_u64()?;
+    Some(DecodedEndorsement {
+        branch,
+        level: level as Level,
+        slot: slot as u16,
+    })
+}
+
+/// The terminal action `prechecker_validate_endorsement` decides on for one endorsement.
+pub enum EndorsementValidationOutcome {
+    Applied(PrecheckerEndorsementValidationAppliedAction),
+    Refused(PrecheckerEndorsementValidationRefusedAction),
+    BranchDelayed(PrecheckerEndorsementValidationBranchDelayedAction),
+    BranchRefused(PrecheckerEndorsementValidationBranchRefusedAction),
+    Outdated(PrecheckerEndorsementValidationOutdatedAction),
+}
+
+/// Verifies a decoded endorsement's signature against the endorsing right for its slot
+/// and classifies it by level/branch against the current head, returning the terminal
+/// action to dispatch next. Returns `None` if the operation isn't far enough along yet
+/// to decide (no decoded contents/raw bytes, or endorsing rights not fetched) - callers
+/// should leave the operation where it is and retry once that changes, rather than
+/// treating it as an error.
+pub fn prechecker_validate_endorsement<V: SignatureVerifier>(
+    verifier: &V,
+    state: &PrecheckerState,
+    action: &PrecheckerValidateEndorsementAction,
+) -> Option<EndorsementValidationOutcome> {
+    let op = state.operations.get(&action.key)?;
+    let contents = op.decoded_contents.as_ref()?;
+    let operation = op.operation.as_ref()?;
+    let protocol_data = contents.contents.clone();
+
+    let decoded = match decode_endorsement(contents) {
+        Some(decoded) => decoded,
+        None => {
+            return Some(EndorsementValidationOutcome::Refused(
+                PrecheckerEndorsementValidationRefusedAction {
+                    key: action.key.clone(),
+                    protocol_data,
+                    error: EndorsementValidationError::Malformed(
+                        "could not parse branch/level/slot from decoded contents".to_string(),
+                    ),
+                },
+            ));
+        }
+    };
+
+    // Endorsing rights are fetched once per head and shared by every endorsement for
+    // that head (see `PrecheckerPrecacheEndorsingRightsAction`); until they land there
+    // is nothing to verify the signature against yet.
+    let endorsing_rights = state.endorsing_rights.as_ref()?;
+    // `EndorsingRights` lives in `crate::rights`; assumed here to expose the delegate's
+    // public key for a given slot, the same lookup the baking/endorsing pipeline needs.
+    let public_key = endorsing_rights.public_key_for_slot(decoded.slot)?;
+
+    let (message, signature) = match split_forged_bytes_and_signature(operation.data()) {
+        Some(split) => split,
+        None => {
+            return Some(EndorsementValidationOutcome::Refused(
+                PrecheckerEndorsementValidationRefusedAction {
+                    key: action.key.clone(),
+                    protocol_data,
+                    error: EndorsementValidationError::Malformed(
+                        "payload is shorter than a signature".to_string(),
+                    ),
+                },
+            ));
+        }
+    };
+    if !verifier.verify_one(public_key, &message, &signature) {
+        return Some(EndorsementValidationOutcome::Refused(
+            PrecheckerEndorsementValidationRefusedAction {
+                key: action.key.clone(),
+                protocol_data,
+                error: EndorsementValidationError::InvalidSignature,
+            },
+        ));
+    }
+
+    let head_level = state.current_head_level;
+    let branch_applied = branch_is_applied(&state.applied_branches, &decoded.branch);
+
+    Some(match classify_endorsement_level(decoded.level, head_level, branch_applied) {
+        EndorsementClass::Applied => {
+            EndorsementValidationOutcome::Applied(PrecheckerEndorsementValidationAppliedAction {
+                key: action.key.clone(),
+                protocol_data,
+            })
+        }
+        EndorsementClass::BranchDelayed => EndorsementValidationOutcome::BranchDelayed(
+            PrecheckerEndorsementValidationBranchDelayedAction {
+                key: action.key.clone(),
+                protocol_data,
+                level: decoded.level,
+                branch: decoded.branch,
+                error: EndorsementValidationError::BranchDelayed {
+                    level: decoded.level,
+                    head_level,
+                },
+            },
+        ),
+        EndorsementClass::BranchRefused => EndorsementValidationOutcome::BranchRefused(
+            PrecheckerEndorsementValidationBranchRefusedAction {
+                key: action.key.clone(),
+                protocol_data,
+                level: decoded.level,
+                branch: decoded.branch,
+                error: EndorsementValidationError::BranchRefused {
+                    level: decoded.level,
+                    head_level,
+                },
+            },
+        ),
+        EndorsementClass::Outdated => {
+            EndorsementValidationOutcome::Outdated(PrecheckerEndorsementValidationOutdatedAction {
+                key: action.key.clone(),
+                protocol_data,
+                error: EndorsementValidationError::Outdated {
+                    level: decoded.level,
+                    head_level,
+                },
+            })
+        }
+    })
+}
+
+/// Validates a manager operation against the account snapshot fetched ahead of time,
+/// reusing `manager_operation_validation_outcome` - the same classification
+/// `validate_manager_operation_batch`'s unit tests exercise. Returns `None` if the
+/// operation isn't far enough along yet (no decoded contents/raw bytes/account).
+pub fn prechecker_validate_manager_operation<V: SignatureVerifier>(
+    verifier: &V,
+    state: &PrecheckerState,
+    action: &PrecheckerValidateManagerOperationAction,
+) -> Option<ManagerOperationOutcome> {
+    let op = state.operations.get(&action.key)?;
+    let contents = op.decoded_contents.as_ref()?;
+    let operation = op.operation.as_ref()?;
+    let account = op.manager_account.as_ref()?;
+    let protocol_data = contents.contents.clone();
+
+    let (message, signature) = match split_forged_bytes_and_signature(operation.data()) {
+        Some(split) => split,
+        None => {
+            return Some(ManagerOperationOutcome::Refused(
+                PrecheckerManagerOperationValidationRefusedAction {
+                    key: action.key.clone(),
+                    protocol_data,
+                    error: ManagerOperationValidationError::Malformed(
+                        "payload is shorter than a signature".to_string(),
+                    ),
+                },
+            ));
+        }
+    };
+    let batch = match decode_manager_operation_contents(contents, message, signature) {
+        Some(batch) => batch,
+        None => {
+            return Some(ManagerOperationOutcome::Refused(
+                PrecheckerManagerOperationValidationRefusedAction {
+                    key: action.key.clone(),
+                    protocol_data,
+                    error: ManagerOperationValidationError::Malformed(
+                        "could not parse manager operation contents".to_string(),
+                    ),
+                },
+            ));
+        }
+    };
+
+    Some(manager_operation_validation_outcome(
+        verifier,
+        action.key.clone(),
+        protocol_data,
+        account,
+        &batch,
+    ))
+}
+
+/// What a single operation in the batch resolves to before signatures are verified.
+enum PendingBatchOperation {
+    /// Hash known, public key known (from already-fetched rights/account state):
+    /// queued for `verify_signatures_parallel`.
+    ToVerify(OperationHash),
+    /// Hash known but we don't have a public key for it yet: same escape hatch the
+    /// single-operation path falls back to when it can't decide.
+    Prevalidate(OperationHash),
+    /// Couldn't even compute a hash for this operation. (A payload too short to carry
+    /// a signature is not caught here: it is left out of verification and refused.)
+    Error(String),
+}
+
+/// Adapts a precomputed `hash -> verified` map (already produced by one
+/// `verify_signatures_parallel` pass over the whole batch) to `SignatureVerifier`, so
+/// `validate_manager_operation_batch` can reuse its counter/gas/fee/balance checks for
+/// one operation's contents without re-verifying its signature a second time.
+struct PrecomputedVerifier<'a> {
+    verified: &'a HashMap<Key, bool>,
+    key: Key,
+}
+
+impl<'a> SignatureVerifier for PrecomputedVerifier<'a> {
+    fn verify_one(&self, _public_key: &str, _message: &[u8], _signature: &[u8]) -> bool {
+        self.verified.get(&self.key).copied().unwrap_or(false)
+    }
+
+    fn verify_batch(&self, _items: &[(String, Vec<u8>, Vec<u8>)]) -> bool {
+        self.verified.get(&self.key).copied().unwrap_or(false)
+    }
+}
+
+/// Turns a classified endorsement into its batch-response shape, reusing the same
+/// `EndorsementValidationError` variants (and their `Display` text) the single-operation
+/// path stores in `PrecheckerOperationState`.
+fn endorsement_batch_response(
+    hash: OperationHash,
+    protocol_data: serde_json::Value,
+    class: EndorsementClass,
+    level: Level,
+    head_level: Level,
+) -> PrecheckerPrecheckOperationResponse {
+    match class {
+        EndorsementClass::Applied => {
+            PrecheckerPrecheckOperationResponse::Applied(PrecheckerApplied { hash, protocol_data })
+        }
+        EndorsementClass::BranchDelayed => PrecheckerPrecheckOperationResponse::BranchDelayed(PrecheckerErrored {
+            hash,
+            protocol_data,
+            error: EndorsementValidationError::BranchDelayed { level, head_level }.to_string(),
+        }),
+        EndorsementClass::BranchRefused => PrecheckerPrecheckOperationResponse::BranchRefused(PrecheckerErrored {
+            hash,
+            protocol_data,
+            error: EndorsementValidationError::BranchRefused { level, head_level }.to_string(),
+        }),
+        EndorsementClass::Outdated => PrecheckerPrecheckOperationResponse::Outdated(PrecheckerErrored {
+            hash,
+            protocol_data,
+            error: EndorsementValidationError::Outdated { level, head_level }.to_string(),
+        }),
+    }
+}
+
+/// Turns a manager operation batch-validation result into its batch-response shape,
+/// reusing `classify_manager_operation_error` - the same mapping
+/// `manager_operation_validation_outcome` uses for the single-operation path.
+fn manager_operation_batch_response(
+    hash: OperationHash,
+    protocol_data: serde_json::Value,
+    outcome: Result<(), ManagerOperationValidationError>,
+) -> PrecheckerPrecheckOperationResponse {
+    match outcome {
+        Ok(()) => PrecheckerPrecheckOperationResponse::Applied(PrecheckerApplied { hash, protocol_data }),
+        Err(error) => {
+            let errored = PrecheckerErrored {
+                hash,
+                protocol_data,
+                error: error.to_string(),
+            };
+            match classify_manager_operation_error(&error) {
+                ManagerOperationClass::Applied => {
+                    unreachable!("classify_manager_operation_error returned Applied for an Err")
+                }
+                ManagerOperationClass::BranchDelayed => {
+                    PrecheckerPrecheckOperationResponse::BranchDelayed(errored)
+                }
+                ManagerOperationClass::Refused => PrecheckerPrecheckOperationResponse::Refused(errored),
+                ManagerOperationClass::Outdated => PrecheckerPrecheckOperationResponse::Outdated(errored),
+            }
+        }
+    }
+}
+
+/// Decodes and verifies a batch of operations without going through the
+/// per-operation action chain: each operation's hash is computed, its forged bytes
+/// and appended signature are split apart, and every signature whose public key is
+/// already known (`known_public_keys`, populated from previously-fetched endorsing
+/// rights/manager accounts) is handed to `verify_signatures_parallel` in one pass.
+/// Once a signature verifies, an operation whose decoded contents are already known
+/// (`decoded_contents`) is classified exactly like the single-operation path: an
+/// endorsement runs through `classify_endorsement_level`, a manager operation runs
+/// through `validate_manager_operation_batch` against its account snapshot
+/// (`manager_accounts`). Operations we can't yet classify fall back to a bare
+/// verified/not-verified response, same as before. Returns one response per input
+/// operation, in input order.
+pub fn prechecker_precheck_operations_batch<V: SignatureVerifier>(
+    verifier: &V,
+    state: &PrecheckerState,
+    known_public_keys: &HashMap<OperationHash, String>,
+    decoded_contents: &HashMap<OperationHash, OperationDecodedContents>,
+    manager_accounts: &HashMap<OperationHash, ManagerAccount>,
+    action: &PrecheckerPrecheckOperationsBatchAction,
+) -> PrecheckerPrecheckOperationsBatchResponseAction {
+    let pending: Vec<PendingBatchOperation> = action
+        .operations
+        .iter()
+        .map(
+            |operation| match operation.message_typed_hash::<OperationHash>() {
+                Err(err) => PendingBatchOperation::Error(err.to_string()),
+                Ok(hash) => match known_public_keys.get(&hash) {
+                    Some(_) => PendingBatchOperation::ToVerify(hash),
+                    None => PendingBatchOperation::Prevalidate(hash),
+                },
+            },
+        )
+        .collect();
+
+    let to_verify = action
+        .operations
+        .iter()
+        .zip(&pending)
+        .filter_map(|(operation, item)| match item {
+            PendingBatchOperation::ToVerify(hash) => {
+                let public_key = known_public_keys.get(hash)?.clone();
+                let (message, signature) = split_forged_bytes_and_signature(operation.data())?;
+                Some(SignatureMaterial {
+                    key: hash.clone(),
+                    public_key,
+                    message,
+                    signature,
+                })
+            }
+            _ => None,
+        })
+        .collect();
+
+    let verified: HashMap<Key, bool> = verify_signatures_parallel(verifier, to_verify).into_iter().collect();
+
+    let responses = action
+        .operations
+        .iter()
+        .zip(pending)
+        .map(|(operation, item)| match item {
+            PendingBatchOperation::Error(error) => PrecheckerPrecheckOperationResponse::Error(
+                super::PrecheckerError::Decode(error).into(),
+            ),
+            PendingBatchOperation::Prevalidate(hash) => {
+                PrecheckerPrecheckOperationResponse::Prevalidate(PrecheckerPrevalidate { hash })
+            }
+            PendingBatchOperation::ToVerify(hash) => {
+                if !verified.get(&hash).copied().unwrap_or(false) {
+                    return PrecheckerPrecheckOperationResponse::Refused(PrecheckerErrored {
+                        hash,
+                        protocol_data: serde_json::Value::Null,
+                        error: "signature does not verify".to_string(),
+                    });
+                }
+
+                let contents = match decoded_contents.get(&hash) {
+                    Some(contents) => contents,
+                    None => {
+                        return PrecheckerPrecheckOperationResponse::Applied(PrecheckerApplied {
+                            hash,
+                            protocol_data: serde_json::Value::Null,
+                        })
+                    }
+                };
+                let protocol_data = contents.contents.clone();
+
+                if let Some(decoded) = decode_endorsement(contents) {
+                    let branch_applied = branch_is_applied(&state.applied_branches, &decoded.branch);
+                    let class =
+                        classify_endorsement_level(decoded.level, state.current_head_level, branch_applied);
+                    return endorsement_batch_response(
+                        hash,
+                        protocol_data,
+                        class,
+                        decoded.level,
+                        state.current_head_level,
+                    );
+                }
+
+                let account = match manager_accounts.get(&hash) {
+                    Some(account) => account,
+                    None => {
+                        return PrecheckerPrecheckOperationResponse::Applied(PrecheckerApplied {
+                            hash,
+                            protocol_data,
+                        })
+                    }
+                };
+                let (message, signature) = match split_forged_bytes_and_signature(operation.data()) {
+                    Some(split) => split,
+                    None => {
+                        return PrecheckerPrecheckOperationResponse::Applied(PrecheckerApplied {
+                            hash,
+                            protocol_data,
+                        })
+                    }
+                };
+                match decode_manager_operation_contents(contents, message, signature) {
+                    Some(batch) => {
+                        let precomputed = PrecomputedVerifier {
+                            verified: &verified,
+                            key: hash.clone(),
+                        };
+                        let outcome = validate_manager_operation_batch(&precomputed, account, &batch);
+                        manager_operation_batch_response(hash, protocol_data, outcome)
+                    }
+                    None => PrecheckerPrecheckOperationResponse::Applied(PrecheckerApplied { hash, protocol_data }),
+                }
+            }
+        })
+        .collect();
+
+    PrecheckerPrecheckOperationsBatchResponseAction { responses }
+}
+
+/// Turns one classified `PrecheckerOperationState` into the RPC-facing shape, or
+/// `None` if the operation hasn't reached a terminal class yet (still being decoded,
+/// waiting on block application, endorsing rights or a manager account read).
+fn mempool_operation(hash: &Key, state: &PrecheckerOperationState) -> Option<PrecheckerMempoolOperation> {
+    let (protocol_data, error) = match state {
+        PrecheckerOperationState::Applied { protocol_data } => (protocol_data, None),
+        PrecheckerOperationState::BranchDelayed {
+            protocol_data,
+            error,
+            ..
+        }
+        | PrecheckerOperationState::BranchRefused {
+            protocol_data,
+            error,
+            ..
+        }
+        | PrecheckerOperationState::Refused {
+            protocol_data,
+            error,
+        }
+        | PrecheckerOperationState::Outdated {
+            protocol_data,
+            error,
+        } => (protocol_data, Some(error.clone())),
+        _ => return None,
+    };
+    Some(PrecheckerMempoolOperation {
+        hash: hash.clone(),
+        protocol_data: protocol_data.clone(),
+        error,
+    })
+}
+
+/// Builds the `GetMempool` entries for one class by iterating its index set instead of
+/// scanning `state.operations`. A key present in the index but missing from
+/// `state.operations`, or whose state is no longer a terminal class at all, is skipped
+/// rather than panicking. An entry whose state sits in a different terminal class than
+/// `index` is still returned; its class is not re-checked against the index here, and
+/// `reindex_operation_class` is what keeps the two in sync.
+fn mempool_class_entries<'a>(
+    state: &'a PrecheckerState,
+    index: &'a std::collections::BTreeSet<Key>,
+) -> Vec<PrecheckerMempoolOperation> {
+    index
+        .iter()
+        .filter_map(|hash| state.operations.get(hash).and_then(|op| mempool_operation(hash, &op.state)))
+        .collect()
+}
+
+/// Answers a `PrecheckerQuery` against the live prechecker state, grouping classified
+/// operations by class for `GetMempool`, looking a single one up by hash for
+/// `GetOperation`, or reporting the aggregate counters for `GetPrecheckerStats`.
+pub fn handle_prechecker_query(state: &PrecheckerState, query: &PrecheckerQuery) -> PrecheckerQueryResponse {
+    match query {
+        PrecheckerQuery::GetMempool => PrecheckerQueryResponse::Mempool(PrecheckerMempool {
+            applied: mempool_class_entries(state, &state.applied),
+            branch_delayed: mempool_class_entries(state, &state.branch_delayed),
+            branch_refused: mempool_class_entries(state, &state.branch_refused),
+            refused: mempool_class_entries(state, &state.refused),
+            outdated: mempool_class_entries(state, &state.outdated),
+        }),
+        PrecheckerQuery::GetOperation { hash } => {
+            let operation = state
+                .operations
+                .get(hash)
+                .and_then(|op| mempool_operation(hash, &op.state));
+            PrecheckerQueryResponse::Operation(operation)
+        }
+        PrecheckerQuery::GetPrecheckerStats => PrecheckerQueryResponse::Stats(PrecheckerStats {
+            applied_count: state.applied_count,
+            refused_count: state.refused_count,
+            avg_precheck_micros: state.avg_precheck_micros(),
+        }),
+    }
+}
+
+/// One operation's signature material: the public key it was signed with, the bytes
+/// that were signed, and the signature itself.
+pub struct SignatureMaterial {
+    pub key: Key,
+    pub public_key: String,
+    pub message: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Abstracts over the actual ed25519 implementation so the grouping/fan-out logic
+/// below is unit-testable without invoking real cryptography.
+pub trait SignatureVerifier: Sync {
+    fn verify_one(&self, public_key: &str, message: &[u8], signature: &[u8]) -> bool;
+    /// Verifies the combined equation for a batch of (public_key, message, signature)
+    /// triples sharing the same curve. Returns false if *any* signature in the batch
+    /// is invalid; callers fall back to `verify_one` per-signature to find which.
+    fn verify_batch(&self, items: &[(String, Vec<u8>, Vec<u8>)]) -> bool;
+}
+
+/// Verifies a batch of operation signatures in parallel with rayon. Operations signed
+/// by the same public key (a flood of endorsements for one block is the common case)
+/// are grouped and checked with one combined ed25519 batch-verify equation; only if
+/// that combined check fails do we fall back to per-signature verification, to pin the
+/// offending operation without penalizing the common all-valid case.
+pub fn verify_signatures_parallel<V: SignatureVerifier>(
+    verifier: &V,
+    operations: Vec<SignatureMaterial>,
+) -> Vec<(Key, bool)> {
+    use rayon::prelude::*;
+
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, op) in operations.iter().enumerate() {
+        groups.entry(op.public_key.clone()).or_default().push(index);
+    }
+
+    let mut results: Vec<Option<bool>> = vec![None; operations.len()];
+    let verified: Vec<(usize, bool)> = groups
+        .into_par_iter()
+        .flat_map_iter(|(public_key, indices)| {
+            if indices.len() > 1 {
+                let batch: Vec<(String, Vec<u8>, Vec<u8>)> = indices
+                    .iter()
+                    .map(|&i| {
+                        (
+                            public_key.clone(),
+                            operations[i].message.clone(),
+                            operations[i].signature.clone(),
+                        )
+                    })
+                    .collect();
+                if verifier.verify_batch(&batch) {
+                    indices.into_iter().map(|i| (i, true)).collect::<Vec<_>>()
+                } else {
+                    indices
+                        .into_iter()
+                        .map(|i| {
+                            let op = &operations[i];
+                            (
+                                i,
+                                verifier.verify_one(&op.public_key, &op.message, &op.signature),
+                            )
+                        })
+                        .collect()
+                }
+            } else {
+                let i = indices[0];
+                let op = &operations[i];
+                vec![(
+                    i,
+                    verifier.verify_one(&op.public_key, &op.message, &op.signature),
+                )]
+            }
+        })
+        .collect();
+
+    for (index, ok) in verified {
+        results[index] = Some(ok);
+    }
+
+    operations
+        .into_iter()
+        .zip(results)
+        .map(|(op, ok)| (op.key, ok.unwrap_or(false)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use crypto::hash::OperationHash;
+
+    fn test_key(byte: u8) -> Key {
+        OperationHash::try_from(vec![byte; 32]).unwrap()
+    }
+
+    #[test]
+    fn reclassify_action_uses_applied_block_level() {
+        assert_eq!(reclassify_action_for_level(42).level, 42);
+    }
+
+    #[test]
+    fn split_forged_bytes_and_signature_splits_trailing_64_bytes() {
+        let mut data = vec![1u8, 2, 3];
+        data.extend(vec![0xffu8; 64]);
+
+        let (message, signature) = split_forged_bytes_and_signature(&data).unwrap();
+
+        assert_eq!(message, vec![1u8, 2, 3]);
+        assert_eq!(signature, vec![0xffu8; 64]);
+    }
+
+    #[test]
+    fn split_forged_bytes_and_signature_rejects_payload_shorter_than_a_signature() {
+        let data = vec![0u8; 32];
+        assert!(split_forged_bytes_and_signature(&data).is_none());
+    }
+
+    #[test]
+    fn decode_endorsement_parses_branch_level_and_slot() {
+        let branch = BlockHash::try_from(vec![7u8; 32]).unwrap();
+        let contents = OperationDecodedContents {
+            contents: serde_json::json!({
+                "branch": branch.to_base58_check(),
+                "contents": [{"kind": "endorsement", "level": 100, "slot": 3}],
+            }),
+        };
+
+        let decoded = decode_endorsement(&contents).unwrap();
+
+        assert_eq!(
+            decoded,
+            DecodedEndorsement {
+                branch,
+                level: 100,
+                slot: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn decode_endorsement_rejects_missing_level() {
+        let branch = BlockHash::try_from(vec![7u8; 32]).unwrap();
+        let contents = OperationDecodedContents {
+            contents: serde_json::json!({
+                "branch": branch.to_base58_check(),
+                "contents": [{"kind": "endorsement", "slot": 3}],
+            }),
+        };
+
+        assert!(decode_endorsement(&contents).is_none());
+    }
+
+    #[test]
+    fn endorsement_batch_response_maps_each_class() {
+        let hash = test_key(10);
+        let protocol_data = serde_json::json!({});
+
+        assert!(matches!(
+            endorsement_batch_response(hash.clone(), protocol_data.clone(), EndorsementClass::Applied, 100, 100),
+            PrecheckerPrecheckOperationResponse::Applied(_)
+        ));
+        assert!(matches!(
+            endorsement_batch_response(
+                hash.clone(),
+                protocol_data.clone(),
+                EndorsementClass::BranchDelayed,
+                105,
+                100
+            ),
+            PrecheckerPrecheckOperationResponse::BranchDelayed(_)
+        ));
+        assert!(matches!(
+            endorsement_batch_response(
+                hash.clone(),
+                protocol_data.clone(),
+                EndorsementClass::BranchRefused,
+                98,
+                100
+            ),
+            PrecheckerPrecheckOperationResponse::BranchRefused(_)
+        ));
+        assert!(matches!(
+            endorsement_batch_response(hash, protocol_data, EndorsementClass::Outdated, 80, 100),
+            PrecheckerPrecheckOperationResponse::Outdated(_)
+        ));
+    }
+
+    #[test]
+    fn manager_operation_batch_response_maps_ok_to_applied_and_err_to_its_class() {
+        let hash = test_key(11);
+
+        assert!(matches!(
+            manager_operation_batch_response(hash.clone(), serde_json::json!({}), Ok(())),
+            PrecheckerPrecheckOperationResponse::Applied(_)
+        ));
+        assert!(matches!(
+            manager_operation_batch_response(
+                hash,
+                serde_json::json!({}),
+                Err(ManagerOperationValidationError::FeeTooLow {
+                    fee: 1,
+                    minimal_fee: 2
+                })
+            ),
+            PrecheckerPrecheckOperationResponse::Refused(_)
+        ));
+    }
+
+    struct CountingVerifier {
+        batch_calls: AtomicUsize,
+        single_calls: AtomicUsize,
+        invalid_signatures: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl SignatureVerifier for CountingVerifier {
+        fn verify_one(&self, _public_key: &str, _message: &[u8], signature: &[u8]) -> bool {
+            self.single_calls.fetch_add(1, Ordering::SeqCst);
+            !self
+                .invalid_signatures
+                .lock()
+                .unwrap()
+                .contains(&signature.to_vec())
+        }
+
+        fn verify_batch(&self, items: &[(String, Vec<u8>, Vec<u8>)]) -> bool {
+            self.batch_calls.fetch_add(1, Ordering::SeqCst);
+            let invalid = self.invalid_signatures.lock().unwrap();
+            items.iter().all(|(_, _, sig)| !invalid.contains(sig))
+        }
+    }
+
+    fn material(byte: u8, public_key: &str) -> SignatureMaterial {
+        SignatureMaterial {
+            key: test_key(byte),
+            public_key: public_key.to_string(),
+            message: vec![byte],
+            signature: vec![byte],
+        }
+    }
+
+    #[test]
+    fn same_key_group_uses_combined_batch_check() {
+        let verifier = CountingVerifier {
+            batch_calls: AtomicUsize::new(0),
+            single_calls: AtomicUsize::new(0),
+            invalid_signatures: Mutex::new(vec![]),
+        };
+        let operations = vec![material(1, "pk-a"), material(2, "pk-a"), material(3, "pk-a")];
+
+        let results = verify_signatures_parallel(&verifier, operations);
+
+        assert!(results.iter().all(|(_, ok)| *ok));
+        assert_eq!(verifier.batch_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(verifier.single_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn failed_batch_falls_back_to_per_signature_to_pin_offender() {
+        let verifier = CountingVerifier {
+            batch_calls: AtomicUsize::new(0),
+            single_calls: AtomicUsize::new(0),
+            invalid_signatures: Mutex::new(vec![vec![2u8]]),
+        };
+        let operations = vec![material(1, "pk-a"), material(2, "pk-a"), material(3, "pk-a")];
+
+        let mut results = verify_signatures_parallel(&verifier, operations);
+        results.sort_by_key(|(key, _)| key.clone());
+
+        assert_eq!(verifier.batch_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(verifier.single_calls.load(Ordering::SeqCst), 3);
+        let ok_count = results.iter().filter(|(_, ok)| *ok).count();
+        assert_eq!(ok_count, 2);
+    }
+
+    #[test]
+    fn singleton_key_group_skips_batch_path() {
+        let verifier = CountingVerifier {
+            batch_calls: AtomicUsize::new(0),
+            single_calls: AtomicUsize::new(0),
+            invalid_signatures: Mutex::new(vec![]),
+        };
+        let operations = vec![material(1, "pk-a"), material(2, "pk-b")];
+
+        let results = verify_signatures_parallel(&verifier, operations);
+
+        assert!(results.iter().all(|(_, ok)| *ok));
+        assert_eq!(verifier.batch_calls.load(Ordering::SeqCst), 0);
+        assert_eq!(verifier.single_calls.load(Ordering::SeqCst), 2);
+    }
+
+    fn op(state: PrecheckerOperationState) -> crate::prechecker::PrecheckerOperation {
+        crate::prechecker::PrecheckerOperation {
+            state,
+            operation: None,
+            decoded_contents: None,
+            manager_account: None,
+        }
+    }
+
+    #[test]
+    fn get_mempool_groups_by_class_and_skips_in_flight_operations() {
+        let mut state = PrecheckerState::default();
+        state.operations.insert(
+            test_key(1),
+            op(PrecheckerOperationState::Applied {
+                protocol_data: serde_json::json!({"kind": "endorsement"}),
+            }),
+        );
+        state.reindex_operation_class(&test_key(1));
+        state.operations.insert(
+            test_key(2),
+            op(PrecheckerOperationState::Refused {
+                protocol_data: serde_json::json!({}),
+                error: "invalid signature".to_string(),
+            }),
+        );
+        state.reindex_operation_class(&test_key(2));
+        state.operations.insert(test_key(3), op(PrecheckerOperationState::PendingEndorsingRights));
+        state.reindex_operation_class(&test_key(3));
+
+        let response = handle_prechecker_query(&state, &PrecheckerQuery::GetMempool);
+
+        match response {
+            PrecheckerQueryResponse::Mempool(mempool) => {
+                assert_eq!(mempool.applied.len(), 1);
+                assert_eq!(mempool.refused.len(), 1);
+                assert_eq!(mempool.branch_delayed.len(), 0);
+                assert_eq!(mempool.refused[0].error.as_deref(), Some("invalid signature"));
+            }
+            other => panic!("expected Mempool response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn get_operation_returns_none_for_unknown_hash() {
+        let state = PrecheckerState::default();
+        let response = handle_prechecker_query(
+            &state,
+            &PrecheckerQuery::GetOperation { hash: test_key(9) },
+        );
+        assert!(matches!(response, PrecheckerQueryResponse::Operation(None)));
+    }
+
+    #[test]
+    fn get_prechecker_stats_reports_counters_and_average() {
+        let mut state = PrecheckerState::default();
+        state.applied_count = 3;
+        state.refused_count = 1;
+        state.precheck_micros_total = 400;
+        state.precheck_count = 4;
+
+        let response = handle_prechecker_query(&state, &PrecheckerQuery::GetPrecheckerStats);
+
+        match response {
+            PrecheckerQueryResponse::Stats(stats) => {
+                assert_eq!(stats.applied_count, 3);
+                assert_eq!(stats.refused_count, 1);
+                assert_eq!(stats.avg_precheck_micros, 100);
+            }
+            other => panic!("expected Stats response, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,471 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use super::{
+    Key, ManagerOperationValidationError, PrecheckerManagerOperationValidationAppliedAction,
+    PrecheckerManagerOperationValidationBranchDelayedAction,
+    PrecheckerManagerOperationValidationOutdatedAction,
+    PrecheckerManagerOperationValidationRefusedAction, SignatureVerifier,
+};
+
+/// Hard protocol caps a single manager operation's gas/storage limit may not exceed.
+pub const HARD_GAS_LIMIT_PER_OPERATION: u64 = 1_040_000;
+pub const HARD_STORAGE_LIMIT_PER_OPERATION: u64 = 60_000;
+
+/// Minimal-fee formula: a flat base plus a per-gas-unit and per-byte component. Mirrors
+/// the shape of the protocol's own minimal fee requirement (a real node reads the exact
+/// constants from the protocol's parametric constants; these are the prechecker's own
+/// conservative defaults for the fast path).
+pub const MINIMAL_FEE_MUTEZ: u64 = 100;
+pub const MINIMAL_FEE_PER_GAS_UNIT_NANOTEZ: u64 = 100;
+pub const MINIMAL_FEE_PER_BYTE_MUTEZ: u64 = 1;
+
+pub fn minimal_fee(gas_limit: u64, size_bytes: u64) -> u64 {
+    MINIMAL_FEE_MUTEZ
+        + (gas_limit * MINIMAL_FEE_PER_GAS_UNIT_NANOTEZ) / 1_000
+        + size_bytes * MINIMAL_FEE_PER_BYTE_MUTEZ
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ManagerOperationKind {
+    Reveal,
+    Transaction,
+    Delegation,
+}
+
+/// The subset of a manager operation content needed to fast-path it: everything the
+/// signature check, hard caps, minimal-fee formula and counter sequencing check
+/// against.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ManagerOperationContent {
+    pub kind: ManagerOperationKind,
+    pub counter: u64,
+    pub fee: u64,
+    pub amount: u64,
+    pub gas_limit: u64,
+    pub storage_limit: u64,
+    pub size_bytes: u64,
+    /// The public key being revealed, for a `Reveal` content; `None` for every other
+    /// kind, which sign against the account's already-revealed key instead.
+    pub revealed_public_key: Option<String>,
+    /// The forged bytes this content's signature covers.
+    pub message: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// The class a manager-operation validation failure maps to. Unlike endorsements,
+/// manager operations never end up `BranchRefused` - they are either valid on top of
+/// the current account state, retryable once a pending counter/reveal lands
+/// (`BranchDelayed`), permanently rejected (`Refused`), or unrecoverably stale
+/// (`Outdated`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManagerOperationClass {
+    Applied,
+    BranchDelayed,
+    Refused,
+    Outdated,
+}
+
+pub fn classify_manager_operation_error(error: &ManagerOperationValidationError) -> ManagerOperationClass {
+    match error {
+        ManagerOperationValidationError::CounterBranchDelayed { .. }
+        | ManagerOperationValidationError::KeyNotRevealed => ManagerOperationClass::BranchDelayed,
+        ManagerOperationValidationError::CounterOutdated { .. } => ManagerOperationClass::Outdated,
+        ManagerOperationValidationError::InvalidSignature
+        | ManagerOperationValidationError::GasLimitExceeded { .. }
+        | ManagerOperationValidationError::StorageLimitExceeded { .. }
+        | ManagerOperationValidationError::FeeTooLow { .. }
+        | ManagerOperationValidationError::BalanceTooLow { .. }
+        | ManagerOperationValidationError::Malformed(_) => ManagerOperationClass::Refused,
+    }
+}
+
+/// Turns the raw JSON of a decoded manager operation into the minimal per-content shape
+/// `validate_manager_operation_batch` needs. Tezos manager operations carry one
+/// signature over the whole forged operation, not one per content, so every content in
+/// the batch shares the same `message`/`signature` pair; `size_bytes` is approximated
+/// as the whole operation's forged length, since the per-content split isn't available
+/// here.
+pub fn decode_manager_operation_contents(
+    contents: &super::OperationDecodedContents,
+    message: Vec<u8>,
+    signature: Vec<u8>,
+) -> Option<Vec<ManagerOperationContent>> {
+    let items = contents.contents.get("contents")?.as_array()?;
+    let size_bytes = message.len() as u64;
+
+    items
+        .iter()
+        .map(|item| {
+            let kind = match item.get("kind")?.as_str()? {
+                "reveal" => ManagerOperationKind::Reveal,
+                "transaction" => ManagerOperationKind::Transaction,
+                "delegation" => ManagerOperationKind::Delegation,
+                _ => return None,
+            };
+            let counter = item.get("counter")?.as_str()?.parse().ok()?;
+            let fee = item.get("fee")?.as_str()?.parse().ok()?;
+            let amount = item
+                .get("amount")
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            let gas_limit = item.get("gas_limit")?.as_str()?.parse().ok()?;
+            let storage_limit = item.get("storage_limit")?.as_str()?.parse().ok()?;
+            let revealed_public_key = item
+                .get("public_key")
+                .and_then(|v| v.as_str())
+                .map(str::to_string);
+
+            Some(ManagerOperationContent {
+                kind,
+                counter,
+                fee,
+                amount,
+                gas_limit,
+                storage_limit,
+                size_bytes,
+                revealed_public_key,
+                message: message.clone(),
+                signature: signature.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Validates a batch of manager operation contents (transaction/reveal/delegation)
+/// against the account snapshot fetched ahead of time. Per content, in the order the
+/// request asked for: (1) the source's key must be revealed - either already on the
+/// account or by a `Reveal` content earlier in the batch or this one itself - and the
+/// content's signature must verify
+/// against it; (2) its counter must be the next expected one, strictly sequential
+/// within the batch; (3) gas/storage must stay within the hard caps and the fee must
+/// cover `minimal_fee`; (4) the source must cover fee (+ amount for transactions).
+///
+/// Returns the error of the first content in the batch that fails, if any; contents before it are
+/// implicitly valid since counters are checked to be strictly sequential.
+pub fn validate_manager_operation_batch<V: SignatureVerifier>(
+    verifier: &V,
+    account: &super::ManagerAccount,
+    batch: &[ManagerOperationContent],
+) -> Result<(), ManagerOperationValidationError> {
+    let mut revealed_public_key = account.public_key.clone();
+    let mut balance = account.balance;
+    let mut expected_counter = account.counter + 1;
+
+    for content in batch {
+        if content.kind == ManagerOperationKind::Reveal {
+            revealed_public_key = content.revealed_public_key.clone().or(revealed_public_key);
+        }
+
+        match &revealed_public_key {
+            None => return Err(ManagerOperationValidationError::KeyNotRevealed),
+            Some(public_key) => {
+                if !verifier.verify_one(public_key, &content.message, &content.signature) {
+                    return Err(ManagerOperationValidationError::InvalidSignature);
+                }
+            }
+        }
+
+        if content.counter < expected_counter {
+            return Err(ManagerOperationValidationError::CounterOutdated {
+                counter: content.counter,
+                expected: expected_counter,
+            });
+        }
+        if content.counter > expected_counter {
+            return Err(ManagerOperationValidationError::CounterBranchDelayed {
+                counter: content.counter,
+                expected: expected_counter,
+            });
+        }
+
+        if content.gas_limit > HARD_GAS_LIMIT_PER_OPERATION {
+            return Err(ManagerOperationValidationError::GasLimitExceeded {
+                gas_limit: content.gas_limit,
+                hard_cap: HARD_GAS_LIMIT_PER_OPERATION,
+            });
+        }
+        if content.storage_limit > HARD_STORAGE_LIMIT_PER_OPERATION {
+            return Err(ManagerOperationValidationError::StorageLimitExceeded {
+                storage_limit: content.storage_limit,
+                hard_cap: HARD_STORAGE_LIMIT_PER_OPERATION,
+            });
+        }
+
+        let minimal = minimal_fee(content.gas_limit, content.size_bytes);
+        if content.fee < minimal {
+            return Err(ManagerOperationValidationError::FeeTooLow {
+                fee: content.fee,
+                minimal_fee: minimal,
+            });
+        }
+
+        let required = match content.fee.checked_add(content.amount) {
+            Some(required) => required,
+            None => {
+                return Err(ManagerOperationValidationError::BalanceTooLow {
+                    balance,
+                    required: u64::MAX,
+                })
+            }
+        };
+        if balance < required {
+            return Err(ManagerOperationValidationError::BalanceTooLow { balance, required });
+        }
+
+        balance -= required;
+        expected_counter += 1;
+    }
+
+    Ok(())
+}
+
+/// The action the prechecker should dispatch once a manager operation batch has been
+/// validated against its account snapshot.
+pub enum ManagerOperationOutcome {
+    Applied(PrecheckerManagerOperationValidationAppliedAction),
+    BranchDelayed(PrecheckerManagerOperationValidationBranchDelayedAction),
+    Refused(PrecheckerManagerOperationValidationRefusedAction),
+    Outdated(PrecheckerManagerOperationValidationOutdatedAction),
+}
+
+pub fn manager_operation_validation_outcome<V: SignatureVerifier>(
+    verifier: &V,
+    key: Key,
+    protocol_data: serde_json::Value,
+    account: &super::ManagerAccount,
+    batch: &[ManagerOperationContent],
+) -> ManagerOperationOutcome {
+    match validate_manager_operation_batch(verifier, account, batch) {
+        Ok(()) => ManagerOperationOutcome::Applied(PrecheckerManagerOperationValidationAppliedAction {
+            key,
+            protocol_data,
+        }),
+        Err(error) => match classify_manager_operation_error(&error) {
+            ManagerOperationClass::Applied => {
+                unreachable!("validate_manager_operation_batch returned Err alongside an Applied class")
+            }
+            ManagerOperationClass::BranchDelayed => ManagerOperationOutcome::BranchDelayed(
+                PrecheckerManagerOperationValidationBranchDelayedAction {
+                    key,
+                    protocol_data,
+                    error,
+                },
+            ),
+            ManagerOperationClass::Refused => {
+                ManagerOperationOutcome::Refused(PrecheckerManagerOperationValidationRefusedAction {
+                    key,
+                    protocol_data,
+                    error,
+                })
+            }
+            ManagerOperationClass::Outdated => {
+                ManagerOperationOutcome::Outdated(PrecheckerManagerOperationValidationOutdatedAction {
+                    key,
+                    protocol_data,
+                    error,
+                })
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prechecker::ManagerAccount;
+
+    const VALID_SIGNATURE: &[u8] = b"valid-signature";
+    const PUBLIC_KEY: &str = "edpk-test";
+
+    /// Accepts exactly `VALID_SIGNATURE` for any public key/message, so tests can
+    /// exercise the signature-check step without depending on real ed25519.
+    struct FixedVerifier;
+
+    impl SignatureVerifier for FixedVerifier {
+        fn verify_one(&self, _public_key: &str, _message: &[u8], signature: &[u8]) -> bool {
+            signature == VALID_SIGNATURE
+        }
+
+        fn verify_batch(&self, items: &[(String, Vec<u8>, Vec<u8>)]) -> bool {
+            items.iter().all(|(_, _, sig)| sig.as_slice() == VALID_SIGNATURE)
+        }
+    }
+
+    fn account(public_key: Option<&str>, counter: u64, balance: u64) -> ManagerAccount {
+        ManagerAccount {
+            public_key: public_key.map(str::to_string),
+            counter,
+            balance,
+        }
+    }
+
+    fn transaction(counter: u64, fee: u64, amount: u64) -> ManagerOperationContent {
+        ManagerOperationContent {
+            kind: ManagerOperationKind::Transaction,
+            counter,
+            fee,
+            amount,
+            gas_limit: 1_000,
+            storage_limit: 0,
+            size_bytes: 10,
+            revealed_public_key: None,
+            message: b"forged-bytes".to_vec(),
+            signature: VALID_SIGNATURE.to_vec(),
+        }
+    }
+
+    #[test]
+    fn sequential_counter_with_sufficient_balance_and_fee_applies() {
+        let acc = account(Some(PUBLIC_KEY), 10, 1_000_000);
+        let fee = minimal_fee(1_000, 10);
+        let batch = vec![transaction(11, fee, 100)];
+        assert!(validate_manager_operation_batch(&FixedVerifier, &acc, &batch).is_ok());
+    }
+
+    #[test]
+    fn invalid_signature_is_refused() {
+        let acc = account(Some(PUBLIC_KEY), 10, 1_000_000);
+        let mut content = transaction(11, minimal_fee(1_000, 10), 0);
+        content.signature = b"forged".to_vec();
+        let batch = vec![content];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(err, ManagerOperationValidationError::InvalidSignature);
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::Refused
+        );
+    }
+
+    #[test]
+    fn stale_counter_is_outdated() {
+        let acc = account(Some(PUBLIC_KEY), 10, 1_000_000);
+        let batch = vec![transaction(10, minimal_fee(1_000, 10), 0)];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::Outdated
+        );
+    }
+
+    #[test]
+    fn ahead_counter_is_branch_delayed() {
+        let acc = account(Some(PUBLIC_KEY), 10, 1_000_000);
+        let batch = vec![transaction(13, minimal_fee(1_000, 10), 0)];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::BranchDelayed
+        );
+    }
+
+    #[test]
+    fn fee_below_minimal_is_refused() {
+        let acc = account(Some(PUBLIC_KEY), 10, 1_000_000);
+        let batch = vec![transaction(11, 1, 0)];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::Refused
+        );
+    }
+
+    #[test]
+    fn balance_below_fee_plus_amount_is_refused() {
+        let acc = account(Some(PUBLIC_KEY), 10, 50);
+        let batch = vec![transaction(11, minimal_fee(1_000, 10), 100)];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::Refused
+        );
+    }
+
+    #[test]
+    fn fee_plus_amount_overflow_is_refused_instead_of_wrapping() {
+        let acc = account(Some(PUBLIC_KEY), 10, u64::MAX);
+        let batch = vec![transaction(11, minimal_fee(1_000, 10), u64::MAX)];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::Refused
+        );
+    }
+
+    #[test]
+    fn unrevealed_key_without_pending_reveal_is_branch_delayed() {
+        let acc = account(None, 10, 1_000_000);
+        let batch = vec![transaction(11, minimal_fee(1_000, 10), 0)];
+        let err = validate_manager_operation_batch(&FixedVerifier, &acc, &batch).unwrap_err();
+        assert_eq!(
+            classify_manager_operation_error(&err),
+            ManagerOperationClass::BranchDelayed
+        );
+    }
+
+    #[test]
+    fn unrevealed_key_fixed_by_preceding_reveal_in_batch_applies() {
+        let acc = account(None, 10, 1_000_000);
+        let reveal = ManagerOperationContent {
+            kind: ManagerOperationKind::Reveal,
+            counter: 11,
+            fee: minimal_fee(0, 5),
+            amount: 0,
+            gas_limit: 0,
+            storage_limit: 0,
+            size_bytes: 5,
+            revealed_public_key: Some(PUBLIC_KEY.to_string()),
+            message: b"forged-bytes".to_vec(),
+            signature: VALID_SIGNATURE.to_vec(),
+        };
+        let batch = vec![reveal, transaction(12, minimal_fee(1_000, 10), 0)];
+        assert!(validate_manager_operation_batch(&FixedVerifier, &acc, &batch).is_ok());
+    }
+
+    #[test]
+    fn decode_manager_operation_contents_parses_transaction_fields() {
+        let contents = super::super::OperationDecodedContents {
+            contents: serde_json::json!({
+                "branch": "some-branch",
+                "contents": [{
+                    "kind": "transaction",
+                    "counter": "11",
+                    "fee": "500",
+                    "amount": "100",
+                    "gas_limit": "1000",
+                    "storage_limit": "0",
+                }],
+            }),
+        };
+
+        let batch =
+            decode_manager_operation_contents(&contents, b"forged-bytes".to_vec(), VALID_SIGNATURE.to_vec())
+                .unwrap();
+
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].kind, ManagerOperationKind::Transaction);
+        assert_eq!(batch[0].counter, 11);
+        assert_eq!(batch[0].fee, 500);
+        assert_eq!(batch[0].amount, 100);
+        assert_eq!(batch[0].gas_limit, 1000);
+        assert_eq!(batch[0].storage_limit, 0);
+        assert_eq!(batch[0].message, b"forged-bytes".to_vec());
+        assert_eq!(batch[0].signature, VALID_SIGNATURE.to_vec());
+    }
+
+    #[test]
+    fn decode_manager_operation_contents_rejects_missing_counter() {
+        let contents = super::super::OperationDecodedContents {
+            contents: serde_json::json!({
+                "branch": "some-branch",
+                "contents": [{"kind": "transaction", "fee": "500"}],
+            }),
+        };
+
+        assert!(
+            decode_manager_operation_contents(&contents, b"forged-bytes".to_vec(), VALID_SIGNATURE.to_vec())
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,621 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use tezos_messages::p2p::encoding::block_header::Level;
+
+use super::{
+    branch_is_applied, classify_endorsement_level, decode_endorsement, EndorsementClass, Key,
+    OperationDecodedContents, PrecheckerCacheAppliedBlockAction,
+    PrecheckerEndorsementValidationAppliedAction, PrecheckerEndorsementValidationBranchDelayedAction,
+    PrecheckerEndorsementValidationBranchRefusedAction, PrecheckerEndorsementValidationOutdatedAction,
+    PrecheckerEndorsementValidationRefusedAction, PrecheckerManagerOperationValidationAppliedAction,
+    PrecheckerManagerOperationValidationBranchDelayedAction,
+    PrecheckerManagerAccountReadyAction, PrecheckerManagerOperationValidationOutdatedAction,
+    PrecheckerManagerOperationValidationRefusedAction, PrecheckerOperation, PrecheckerOperationDecodedAction,
+    PrecheckerOperationState, PrecheckerPrecheckOperationResponse, PrecheckerPrecheckOperationsBatchResponseAction,
+    PrecheckerPruneOperationAction, PrecheckerReclassifyOperationsAction, PrecheckerState, RECLASSIFICATION_WINDOW,
+};
+
+/// Persists the decoded contents onto the operation so later stages (endorsement
+/// validation, manager-operation validation) can read them back out without
+/// re-decoding the raw bytes - the pipeline state itself only ever reflects the
+/// *current* stage and is overwritten by every later transition.
+pub fn prechecker_reduce_operation_decoded(
+    state: &mut PrecheckerState,
+    action: &PrecheckerOperationDecodedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.decoded_contents = Some(action.contents.clone());
+        op.state = PrecheckerOperationState::Decoded {
+            contents: action.contents.clone(),
+        };
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_endorsement_validation_applied(
+    state: &mut PrecheckerState,
+    action: &PrecheckerEndorsementValidationAppliedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::Applied {
+            protocol_data: action.protocol_data.clone(),
+        };
+        state.applied_count += 1;
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_endorsement_validation_refused(
+    state: &mut PrecheckerState,
+    action: &PrecheckerEndorsementValidationRefusedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::Refused {
+            protocol_data: action.protocol_data.clone(),
+            error: action.error.to_string(),
+        };
+        state.refused_count += 1;
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_endorsement_validation_branch_delayed(
+    state: &mut PrecheckerState,
+    action: &PrecheckerEndorsementValidationBranchDelayedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::BranchDelayed {
+            protocol_data: action.protocol_data.clone(),
+            level: Some(action.level),
+            branch: Some(action.branch.clone()),
+            error: action.error.to_string(),
+        };
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_endorsement_validation_branch_refused(
+    state: &mut PrecheckerState,
+    action: &PrecheckerEndorsementValidationBranchRefusedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::BranchRefused {
+            protocol_data: action.protocol_data.clone(),
+            level: action.level,
+            branch: action.branch.clone(),
+            error: action.error.to_string(),
+        };
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_endorsement_validation_outdated(
+    state: &mut PrecheckerState,
+    action: &PrecheckerEndorsementValidationOutdatedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::Outdated {
+            protocol_data: action.protocol_data.clone(),
+            error: action.error.to_string(),
+        };
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_manager_operation_validation_applied(
+    state: &mut PrecheckerState,
+    action: &PrecheckerManagerOperationValidationAppliedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::Applied {
+            protocol_data: action.protocol_data.clone(),
+        };
+        state.applied_count += 1;
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_manager_operation_validation_refused(
+    state: &mut PrecheckerState,
+    action: &PrecheckerManagerOperationValidationRefusedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::Refused {
+            protocol_data: action.protocol_data.clone(),
+            error: action.error.to_string(),
+        };
+        state.refused_count += 1;
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+/// Manager operations have no block level of their own to key a retry off, so `level`
+/// is left `None` - unlike the endorsement path, this is never picked up by
+/// `prechecker_reduce_reclassify_operations` on a head change. It is only retried once
+/// the batch/account read that produced this `BranchDelayed` is re-run.
+pub fn prechecker_reduce_manager_operation_validation_branch_delayed(
+    state: &mut PrecheckerState,
+    action: &PrecheckerManagerOperationValidationBranchDelayedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::BranchDelayed {
+            protocol_data: action.protocol_data.clone(),
+            level: None,
+            branch: None,
+            error: action.error.to_string(),
+        };
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_manager_operation_validation_outdated(
+    state: &mut PrecheckerState,
+    action: &PrecheckerManagerOperationValidationOutdatedAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.state = PrecheckerOperationState::Outdated {
+            protocol_data: action.protocol_data.clone(),
+            error: action.error.to_string(),
+        };
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+/// Persists the account snapshot onto the operation once fetched, mirroring
+/// `prechecker_reduce_operation_decoded`, and moves it on to precheck proper.
+pub fn prechecker_reduce_manager_account_ready(
+    state: &mut PrecheckerState,
+    action: &PrecheckerManagerAccountReadyAction,
+) {
+    if let Some(op) = state.operations.get_mut(&action.key) {
+        op.manager_account = Some(action.account.clone());
+        op.state = PrecheckerOperationState::PendingOperationPrechecking;
+    }
+    state.reindex_operation_class(&action.key);
+}
+
+pub fn prechecker_reduce_cache_applied_block(
+    state: &mut PrecheckerState,
+    action: &PrecheckerCacheAppliedBlockAction,
+) {
+    state.current_head_level = action.block_header.level();
+
+    state.applied_branches.push_back(action.block_hash.clone());
+    while state.applied_branches.len() > RECLASSIFICATION_WINDOW as usize + 1 {
+        state.applied_branches.pop_front();
+    }
+}
+
+/// Re-runs `classify_endorsement_level` over every endorsement currently `BranchDelayed`
+/// / `BranchRefused` against the new head, promoting/demoting it or pruning it to
+/// `Outdated`. `BranchDelayed` manager operations (no `level`), `Refused` and `Outdated`
+/// operations are left untouched.
+pub fn prechecker_reduce_reclassify_operations(
+    state: &mut PrecheckerState,
+    action: &PrecheckerReclassifyOperationsAction,
+) {
+    let head_level = action.level;
+    let candidates: Vec<Key> = state
+        .operations
+        .iter()
+        .filter(|(_, op)| {
+            matches!(
+                op.state,
+                PrecheckerOperationState::BranchDelayed { level: Some(_), .. }
+                    | PrecheckerOperationState::BranchRefused { .. }
+            )
+        })
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    for key in candidates {
+        let (protocol_data, level, branch, error) = match &state.operations.get(&key).unwrap().state
+        {
+            PrecheckerOperationState::BranchDelayed {
+                protocol_data,
+                level: Some(level),
+                branch: Some(branch),
+                error,
+            } => (protocol_data.clone(), *level, branch.clone(), error.clone()),
+            PrecheckerOperationState::BranchRefused {
+                protocol_data,
+                level,
+                branch,
+                error,
+            } => (protocol_data.clone(), *level, branch.clone(), error.clone()),
+            _ => continue,
+        };
+
+        // The operation was already valid on its own branch; all we re-evaluate here is
+        // whether that specific branch has actually been applied, not merely whether its
+        // level happens to equal the new head (a head that advances by more than one
+        // level between reclassifications must not be mistaken for "this branch won").
+        let branch_applied = branch_is_applied(&state.applied_branches, &branch);
+
+        let op = state.operations.get_mut(&key).unwrap();
+        op.state = match classify_endorsement_level(level, head_level, branch_applied) {
+            EndorsementClass::Applied => PrecheckerOperationState::Applied { protocol_data },
+            EndorsementClass::BranchDelayed => PrecheckerOperationState::BranchDelayed {
+                protocol_data,
+                level: Some(level),
+                branch: Some(branch),
+                error,
+            },
+            EndorsementClass::BranchRefused => PrecheckerOperationState::BranchRefused {
+                protocol_data,
+                level,
+                branch,
+                error,
+            },
+            EndorsementClass::Outdated => PrecheckerOperationState::Outdated {
+                protocol_data,
+                error,
+            },
+        };
+        state.reindex_operation_class(&key);
+    }
+}
+
+pub fn prechecker_reduce_prune_operation(
+    state: &mut PrecheckerState,
+    action: &PrecheckerPruneOperationAction,
+) {
+    state.operations.remove(&action.key);
+    state.reindex_operation_class(&action.key);
+}
+
+fn batch_response_entry(state: &mut PrecheckerState, hash: &Key) -> &mut PrecheckerOperation {
+    state.operations.entry(hash.clone()).or_insert_with(|| PrecheckerOperation {
+        state: PrecheckerOperationState::Init,
+        operation: None,
+        decoded_contents: None,
+        manager_account: None,
+    })
+}
+
+/// Writes the outcome of `prechecker_precheck_operations_batch` into `state.operations`
+/// and `applied_count`/`refused_count`, the same bookkeeping every single-operation
+/// validation reducer above does - the batch path skips the per-operation action chain,
+/// but its results land in the same place. An operation not already tracked (the batch
+/// path can run without ever going through `PrecheckerPrecheckOperationRequestAction`)
+/// is inserted fresh.
+pub fn prechecker_reduce_precheck_operations_batch_response(
+    state: &mut PrecheckerState,
+    action: &PrecheckerPrecheckOperationsBatchResponseAction,
+) {
+    for response in &action.responses {
+        match response {
+            PrecheckerPrecheckOperationResponse::Applied(applied) => {
+                let op = batch_response_entry(state, &applied.hash);
+                op.state = PrecheckerOperationState::Applied {
+                    protocol_data: applied.protocol_data.clone(),
+                };
+                state.applied_count += 1;
+                state.reindex_operation_class(&applied.hash);
+            }
+            PrecheckerPrecheckOperationResponse::Refused(errored) => {
+                let op = batch_response_entry(state, &errored.hash);
+                op.state = PrecheckerOperationState::Refused {
+                    protocol_data: errored.protocol_data.clone(),
+                    error: errored.error.clone(),
+                };
+                state.refused_count += 1;
+                state.reindex_operation_class(&errored.hash);
+            }
+            PrecheckerPrecheckOperationResponse::BranchDelayed(errored) => {
+                // Endorsements carry their level/branch in `protocol_data`; manager
+                // operations have neither (see `PrecheckerManagerOperationValidationBranchDelayedAction`),
+                // so `decode_endorsement` returning `None` there is the expected case, not
+                // an error.
+                let decoded = decode_endorsement(&OperationDecodedContents {
+                    contents: errored.protocol_data.clone(),
+                });
+                let op = batch_response_entry(state, &errored.hash);
+                op.state = PrecheckerOperationState::BranchDelayed {
+                    protocol_data: errored.protocol_data.clone(),
+                    level: decoded.as_ref().map(|d| d.level),
+                    branch: decoded.map(|d| d.branch),
+                    error: errored.error.clone(),
+                };
+                state.reindex_operation_class(&errored.hash);
+            }
+            PrecheckerPrecheckOperationResponse::BranchRefused(errored) => {
+                // Only endorsements are ever classified `BranchRefused` in the batch path
+                // (`ManagerOperationClass` has no such variant), so `protocol_data` always
+                // decodes as one here.
+                let decoded = decode_endorsement(&OperationDecodedContents {
+                    contents: errored.protocol_data.clone(),
+                });
+                let op = batch_response_entry(state, &errored.hash);
+                op.state = match decoded {
+                    Some(decoded) => PrecheckerOperationState::BranchRefused {
+                        protocol_data: errored.protocol_data.clone(),
+                        level: decoded.level,
+                        branch: decoded.branch,
+                        error: errored.error.clone(),
+                    },
+                    None => PrecheckerOperationState::Refused {
+                        protocol_data: errored.protocol_data.clone(),
+                        error: errored.error.clone(),
+                    },
+                };
+                state.reindex_operation_class(&errored.hash);
+            }
+            PrecheckerPrecheckOperationResponse::Outdated(errored) => {
+                let op = batch_response_entry(state, &errored.hash);
+                op.state = PrecheckerOperationState::Outdated {
+                    protocol_data: errored.protocol_data.clone(),
+                    error: errored.error.clone(),
+                };
+                state.reindex_operation_class(&errored.hash);
+            }
+            PrecheckerPrecheckOperationResponse::Prevalidate(_) | PrecheckerPrecheckOperationResponse::Error(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prechecker::{ManagerAccount, PrecheckerOperation};
+    use crypto::hash::OperationHash;
+
+    fn test_key(byte: u8) -> Key {
+        OperationHash::try_from(vec![byte; 32]).unwrap()
+    }
+
+    fn test_branch(byte: u8) -> crypto::hash::BlockHash {
+        crypto::hash::BlockHash::try_from(vec![byte; 32]).unwrap()
+    }
+
+    fn insert_branch_delayed(
+        state: &mut PrecheckerState,
+        key: Key,
+        level: Level,
+        branch: crypto::hash::BlockHash,
+    ) {
+        state.operations.insert(
+            key,
+            PrecheckerOperation {
+                state: PrecheckerOperationState::BranchDelayed {
+                    protocol_data: serde_json::json!({}),
+                    level: Some(level),
+                    branch: Some(branch),
+                    error: "pending".to_string(),
+                },
+                operation: None,
+                decoded_contents: None,
+                manager_account: None,
+            },
+        );
+    }
+
+    #[test]
+    fn reclassify_promotes_branch_delayed_to_applied_once_head_catches_up() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(1);
+        let branch = test_branch(1);
+        insert_branch_delayed(&mut state, key.clone(), 105, branch.clone());
+        state.applied_branches.push_back(branch);
+
+        prechecker_reduce_reclassify_operations(
+            &mut state,
+            &PrecheckerReclassifyOperationsAction { level: 105 },
+        );
+
+        assert!(matches!(
+            state.operations.get(&key).unwrap().state,
+            PrecheckerOperationState::Applied { .. }
+        ));
+    }
+
+    #[test]
+    fn reclassify_prunes_to_outdated_once_window_elapses() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(2);
+        insert_branch_delayed(&mut state, key.clone(), 100, test_branch(2));
+
+        prechecker_reduce_reclassify_operations(
+            &mut state,
+            &PrecheckerReclassifyOperationsAction {
+                level: 100 + RECLASSIFICATION_WINDOW + 1,
+            },
+        );
+
+        assert!(matches!(
+            state.operations.get(&key).unwrap().state,
+            PrecheckerOperationState::Outdated { .. }
+        ));
+    }
+
+    #[test]
+    fn reclassify_stays_branch_delayed_when_branch_was_never_applied() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(5);
+        insert_branch_delayed(&mut state, key.clone(), 100, test_branch(5));
+        state.applied_branches.push_back(test_branch(9));
+
+        prechecker_reduce_reclassify_operations(
+            &mut state,
+            &PrecheckerReclassifyOperationsAction { level: 100 },
+        );
+
+        assert!(matches!(
+            state.operations.get(&key).unwrap().state,
+            PrecheckerOperationState::BranchDelayed { level: Some(100), .. }
+        ));
+    }
+
+    #[test]
+    fn reclassify_leaves_manager_operation_branch_delayed_untouched() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(3);
+        state.operations.insert(
+            key.clone(),
+            PrecheckerOperation {
+                state: PrecheckerOperationState::BranchDelayed {
+                    protocol_data: serde_json::json!({}),
+                    level: None,
+                    branch: None,
+                    error: "counter ahead of account".to_string(),
+                },
+                operation: None,
+                decoded_contents: None,
+                manager_account: None,
+            },
+        );
+
+        prechecker_reduce_reclassify_operations(
+            &mut state,
+            &PrecheckerReclassifyOperationsAction { level: 100 },
+        );
+
+        assert!(matches!(
+            state.operations.get(&key).unwrap().state,
+            PrecheckerOperationState::BranchDelayed { level: None, .. }
+        ));
+    }
+
+    #[test]
+    fn manager_operation_validation_applied_sets_applied_state_and_count() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(4);
+        state.operations.insert(
+            key.clone(),
+            PrecheckerOperation {
+                state: PrecheckerOperationState::PendingOperationPrechecking,
+                operation: None,
+                decoded_contents: None,
+                manager_account: None,
+            },
+        );
+
+        prechecker_reduce_manager_operation_validation_applied(
+            &mut state,
+            &PrecheckerManagerOperationValidationAppliedAction {
+                key: key.clone(),
+                protocol_data: serde_json::json!({}),
+            },
+        );
+
+        assert!(matches!(
+            state.operations.get(&key).unwrap().state,
+            PrecheckerOperationState::Applied { .. }
+        ));
+        assert_eq!(state.applied_count, 1);
+    }
+
+    #[test]
+    fn manager_account_ready_stores_account_and_advances_to_pending_precheck() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(6);
+        state.operations.insert(
+            key.clone(),
+            PrecheckerOperation {
+                state: PrecheckerOperationState::PendingManagerAccount,
+                operation: None,
+                decoded_contents: None,
+                manager_account: None,
+            },
+        );
+        let account = ManagerAccount {
+            public_key: Some("edpk-test".to_string()),
+            counter: 10,
+            balance: 1_000_000,
+        };
+
+        prechecker_reduce_manager_account_ready(
+            &mut state,
+            &PrecheckerManagerAccountReadyAction {
+                key: key.clone(),
+                account: account.clone(),
+            },
+        );
+
+        let op = state.operations.get(&key).unwrap();
+        assert!(matches!(
+            op.state,
+            PrecheckerOperationState::PendingOperationPrechecking
+        ));
+        assert_eq!(op.manager_account.as_ref().unwrap().balance, account.balance);
+    }
+
+    #[test]
+    fn batch_response_applied_inserts_operation_and_bumps_count() {
+        let mut state = PrecheckerState::default();
+        let hash = test_key(7);
+
+        prechecker_reduce_precheck_operations_batch_response(
+            &mut state,
+            &PrecheckerPrecheckOperationsBatchResponseAction {
+                responses: vec![PrecheckerPrecheckOperationResponse::Applied(
+                    crate::prechecker::PrecheckerApplied {
+                        hash: hash.clone(),
+                        protocol_data: serde_json::json!({}),
+                    },
+                )],
+            },
+        );
+
+        assert!(matches!(
+            state.operations.get(&hash).unwrap().state,
+            PrecheckerOperationState::Applied { .. }
+        ));
+        assert_eq!(state.applied_count, 1);
+    }
+
+    #[test]
+    fn batch_response_branch_refused_recovers_level_and_branch_from_protocol_data() {
+        let mut state = PrecheckerState::default();
+        let hash = test_key(8);
+        let branch = test_branch(8);
+        let protocol_data = serde_json::json!({
+            "branch": branch.to_base58_check(),
+            "contents": [{"kind": "endorsement", "level": 98, "slot": 0}],
+        });
+
+        prechecker_reduce_precheck_operations_batch_response(
+            &mut state,
+            &PrecheckerPrecheckOperationsBatchResponseAction {
+                responses: vec![PrecheckerPrecheckOperationResponse::BranchRefused(
+                    crate::prechecker::PrecheckerErrored {
+                        hash: hash.clone(),
+                        protocol_data,
+                        error: "endorsement level 98 is behind head 100 or on another branch".to_string(),
+                    },
+                )],
+            },
+        );
+
+        assert!(matches!(
+            state.operations.get(&hash).unwrap().state,
+            PrecheckerOperationState::BranchRefused { level: 98, branch: ref b, .. } if *b == branch
+        ));
+    }
+
+    #[test]
+    fn batch_response_refused_bumps_refused_count() {
+        let mut state = PrecheckerState::default();
+        let hash = test_key(9);
+
+        prechecker_reduce_precheck_operations_batch_response(
+            &mut state,
+            &PrecheckerPrecheckOperationsBatchResponseAction {
+                responses: vec![PrecheckerPrecheckOperationResponse::Refused(
+                    crate::prechecker::PrecheckerErrored {
+                        hash: hash.clone(),
+                        protocol_data: serde_json::json!({}),
+                        error: "signature does not verify".to_string(),
+                    },
+                )],
+            },
+        );
+
+        assert!(matches!(
+            state.operations.get(&hash).unwrap().state,
+            PrecheckerOperationState::Refused { .. }
+        ));
+        assert_eq!(state.refused_count, 1);
+    }
+}
@@ -0,0 +1,48 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use crypto::hash::OperationHash;
+
+/// Typed, serializable surface for introspecting the prechecker's live mempool
+/// classification, mirroring the visibility the protocol prevalidator already
+/// exposes over RPC.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "method", content = "params")]
+pub enum PrecheckerQuery {
+    /// Returns the current mempool grouped by class.
+    GetMempool,
+    /// Returns a single operation's classification, if the prechecker knows about it.
+    GetOperation { hash: OperationHash },
+    /// Returns aggregate precheck counters.
+    GetPrecheckerStats,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerMempool {
+    pub applied: Vec<PrecheckerMempoolOperation>,
+    pub branch_delayed: Vec<PrecheckerMempoolOperation>,
+    pub branch_refused: Vec<PrecheckerMempoolOperation>,
+    pub refused: Vec<PrecheckerMempoolOperation>,
+    pub outdated: Vec<PrecheckerMempoolOperation>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerMempoolOperation {
+    pub hash: OperationHash,
+    pub protocol_data: serde_json::Value,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerStats {
+    pub applied_count: u64,
+    pub refused_count: u64,
+    pub avg_precheck_micros: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum PrecheckerQueryResponse {
+    Mempool(PrecheckerMempool),
+    Operation(Option<PrecheckerMempoolOperation>),
+    Stats(PrecheckerStats),
+}
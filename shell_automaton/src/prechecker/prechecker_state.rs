@@ -0,0 +1,385 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crypto::hash::{BlockHash, OperationHash};
+use tezos_messages::p2p::encoding::block_header::Level;
+
+use crate::rights::EndorsingRights;
+
+/// Operations are tracked by their hash throughout prechecking.
+pub type Key = OperationHash;
+
+/// How many levels below the current head a branch-refused endorsement may still sit
+/// before it is reclassified as `Outdated` and pruned for good.
+pub const RECLASSIFICATION_WINDOW: Level = 5;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum SupportedProtocolState {
+    None,
+    Requesting(BlockHash),
+    Ready(tezos_messages::protocol::SupportedProtocol),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OperationDecodedContents {
+    pub contents: serde_json::Value,
+}
+
+/// Minimal context snapshot needed to fast-path a manager operation: the source's
+/// manager key, if already revealed on-chain, its current counter and its spendable
+/// balance. The public key is what lets the fast path actually verify a manager
+/// operation's signature instead of just checking whether a reveal happened.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ManagerAccount {
+    pub public_key: Option<String>,
+    pub counter: u64,
+    pub balance: u64,
+}
+
+#[derive(Debug, Clone, thiserror::Error, serde::Serialize, serde::Deserialize)]
+pub enum PrecheckerError {
+    #[error("endorsement validation error: {0}")]
+    EndorsementValidation(#[from] EndorsementValidationError),
+    #[error("manager operation validation error: {0}")]
+    ManagerOperationValidation(#[from] ManagerOperationValidationError),
+    #[error("operation decode error: {0}")]
+    Decode(String),
+}
+
+#[derive(Debug, Clone, thiserror::Error, serde::Serialize, serde::Deserialize)]
+pub enum PrecheckerResponseError {
+    #[error("{0}")]
+    Prechecker(#[from] PrecheckerError),
+}
+
+#[derive(Debug, Clone, thiserror::Error, serde::Serialize, serde::Deserialize)]
+pub enum EndorsementValidationError {
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("endorsement level {level} is ahead of or on an unknown branch relative to head {head_level}")]
+    BranchDelayed { level: Level, head_level: Level },
+    #[error("endorsement level {level} is behind head {head_level} or on another branch")]
+    BranchRefused { level: Level, head_level: Level },
+    #[error("endorsement level {level} is too old relative to head {head_level}")]
+    Outdated { level: Level, head_level: Level },
+    #[error("could not decode endorsement contents: {0}")]
+    Malformed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, serde::Serialize, serde::Deserialize)]
+pub enum ManagerOperationValidationError {
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("gas limit {gas_limit} exceeds hard cap {hard_cap}")]
+    GasLimitExceeded { gas_limit: u64, hard_cap: u64 },
+    #[error("storage limit {storage_limit} exceeds hard cap {hard_cap}")]
+    StorageLimitExceeded { storage_limit: u64, hard_cap: u64 },
+    #[error("fee {fee} is below the minimal fee {minimal_fee}")]
+    FeeTooLow { fee: u64, minimal_fee: u64 },
+    #[error("balance {balance} does not cover fee and amount {required}")]
+    BalanceTooLow { balance: u64, required: u64 },
+    #[error("counter {counter} is not sequential, expected {expected}")]
+    CounterBranchDelayed { counter: u64, expected: u64 },
+    #[error("counter {counter} is stale, expected {expected}")]
+    CounterOutdated { counter: u64, expected: u64 },
+    #[error("manager key is not revealed and no preceding Reveal was found in the batch")]
+    KeyNotRevealed,
+    #[error("could not decode manager operation contents: {0}")]
+    Malformed(String),
+}
+
+/// Where a single operation is in the precheck pipeline, ending in one of the five
+/// Tezos-style mempool classes.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum PrecheckerOperationState {
+    Init,
+    PendingProtocolVersion,
+    Decoded {
+        contents: OperationDecodedContents,
+    },
+    WaitingForBlockApplication {
+        level: Level,
+    },
+    PendingEndorsingRights,
+    PendingManagerAccount,
+    PendingOperationPrechecking,
+    Applied {
+        protocol_data: serde_json::Value,
+    },
+    BranchDelayed {
+        protocol_data: serde_json::Value,
+        /// `Some(level)` for an endorsement, reclassified against the head level on
+        /// every `PrecheckerCacheAppliedBlockAction`. `None` for a manager operation -
+        /// it has no block level of its own and is only retried once the batch/account
+        /// read that produced this state is re-run, never by the endorsement
+        /// reclassification pass.
+        level: Option<Level>,
+        /// The block this endorsement was built on, alongside `level`; `None` for a
+        /// manager operation, which has no branch of its own to track either.
+        branch: Option<BlockHash>,
+        error: String,
+    },
+    BranchRefused {
+        protocol_data: serde_json::Value,
+        level: Level,
+        /// The block this endorsement was built on. Reclassification checks whether
+        /// this specific block - not just `level` - is among the recently applied
+        /// branches, so a head that jumps past `level` without ever applying this
+        /// branch doesn't get mistaken for "this endorsement's branch was applied".
+        branch: BlockHash,
+        error: String,
+    },
+    Refused {
+        protocol_data: serde_json::Value,
+        error: String,
+    },
+    Outdated {
+        protocol_data: serde_json::Value,
+        error: String,
+    },
+    ProtocolNeeded,
+    Error {
+        error: PrecheckerError,
+    },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerOperation {
+    pub state: PrecheckerOperationState,
+    pub operation: Option<tezos_messages::p2p::encoding::operation::Operation>,
+    /// Populated once `PrecheckerOperationDecodedAction` lands; read back out by
+    /// `prechecker_validate_endorsement`/the manager-operation validation effect
+    /// instead of re-decoding the raw operation bytes.
+    pub decoded_contents: Option<OperationDecodedContents>,
+    /// Populated once `PrecheckerManagerAccountReadyAction` lands, for manager
+    /// operations only.
+    pub manager_account: Option<ManagerAccount>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PrecheckerState {
+    pub operations: BTreeMap<Key, PrecheckerOperation>,
+    pub next_protocol: Option<(u8, SupportedProtocolState)>,
+    pub endorsing_rights: Option<EndorsingRights>,
+    pub current_head_level: Level,
+    /// The block hashes applied over the last `RECLASSIFICATION_WINDOW` levels, most
+    /// recently applied last. Whether an endorsement's declared branch is actually
+    /// applied is answered by membership here, not by comparing levels - a head that
+    /// advances by more than one level between `PrecheckerCacheAppliedBlockAction`s
+    /// (bootstrap catch-up, multi-block import) must not make an endorsement's branch
+    /// look unapplied just because it no longer equals the latest head level.
+    pub applied_branches: VecDeque<BlockHash>,
+    pub applied_count: u64,
+    pub refused_count: u64,
+    pub precheck_micros_total: u64,
+    pub precheck_count: u64,
+    /// One `Key` set per terminal mempool class, kept in sync with `operations` by
+    /// `reindex_operation_class` on every reducer that changes an operation's state, so
+    /// `GetMempool` can answer by iterating the class it was asked for instead of
+    /// scanning and re-matching every tracked operation.
+    pub applied: BTreeSet<Key>,
+    pub branch_delayed: BTreeSet<Key>,
+    pub branch_refused: BTreeSet<Key>,
+    pub refused: BTreeSet<Key>,
+    pub outdated: BTreeSet<Key>,
+}
+
+impl PrecheckerState {
+    pub fn avg_precheck_micros(&self) -> u64 {
+        if self.precheck_count == 0 {
+            0
+        } else {
+            self.precheck_micros_total / self.precheck_count
+        }
+    }
+
+    /// Removes `key` from every per-class index set, then re-inserts it into the one
+    /// matching its current `operations[key].state`, if any. Must be called after every
+    /// mutation of an operation's `state` field; if `key` isn't tracked in `operations`
+    /// at all, it is just removed from every set.
+    pub fn reindex_operation_class(&mut self, key: &Key) {
+        self.applied.remove(key);
+        self.branch_delayed.remove(key);
+        self.branch_refused.remove(key);
+        self.refused.remove(key);
+        self.outdated.remove(key);
+
+        let set = match self.operations.get(key).map(|op| &op.state) {
+            Some(PrecheckerOperationState::Applied { .. }) => &mut self.applied,
+            Some(PrecheckerOperationState::BranchDelayed { .. }) => &mut self.branch_delayed,
+            Some(PrecheckerOperationState::BranchRefused { .. }) => &mut self.branch_refused,
+            Some(PrecheckerOperationState::Refused { .. }) => &mut self.refused,
+            Some(PrecheckerOperationState::Outdated { .. }) => &mut self.outdated,
+            _ => return,
+        };
+        set.insert(key.clone());
+    }
+}
+
+/// The four Tezos-style mempool classes an endorsement whose signature verified can end
+/// up in (`Refused` is decided before classification), independent of whatever state
+/// machine bookkeeping wraps them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndorsementClass {
+    Applied,
+    BranchDelayed,
+    BranchRefused,
+    Outdated,
+}
+
+/// Whether `branch` is among the recently applied block hashes tracked in
+/// `PrecheckerState::applied_branches`.
+pub fn branch_is_applied(applied_branches: &VecDeque<BlockHash>, branch: &BlockHash) -> bool {
+    applied_branches.contains(branch)
+}
+
+/// Classifies an endorsement whose signature already verified, based on its level and
+/// whether the branch it was built on has been applied.
+///
+/// - ahead of the head, or on a branch we haven't applied yet: `BranchDelayed` (may
+///   become valid once we advance, retry later);
+/// - behind the head (but within the reclassification window) or on a different,
+///   already-applied branch: `BranchRefused` (valid elsewhere, not on this branch);
+/// - more than `RECLASSIFICATION_WINDOW` levels behind the head: `Outdated`, never
+///   re-evaluated again;
+/// - exactly at the head, on the applied branch: `Applied`.
+pub fn classify_endorsement_level(
+    op_level: Level,
+    current_head_level: Level,
+    branch_applied: bool,
+) -> EndorsementClass {
+    if current_head_level.saturating_sub(op_level) > RECLASSIFICATION_WINDOW {
+        EndorsementClass::Outdated
+    } else if op_level > current_head_level || !branch_applied {
+        EndorsementClass::BranchDelayed
+    } else if op_level < current_head_level {
+        EndorsementClass::BranchRefused
+    } else {
+        EndorsementClass::Applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ahead_of_head_is_branch_delayed() {
+        assert_eq!(
+            classify_endorsement_level(105, 100, true),
+            EndorsementClass::BranchDelayed
+        );
+    }
+
+    #[test]
+    fn unknown_branch_is_branch_delayed() {
+        assert_eq!(
+            classify_endorsement_level(100, 100, false),
+            EndorsementClass::BranchDelayed
+        );
+    }
+
+    #[test]
+    fn behind_head_on_applied_branch_is_branch_refused() {
+        assert_eq!(
+            classify_endorsement_level(98, 100, true),
+            EndorsementClass::BranchRefused
+        );
+    }
+
+    #[test]
+    fn far_behind_head_is_outdated() {
+        assert_eq!(
+            classify_endorsement_level(
+                100 - RECLASSIFICATION_WINDOW - 1,
+                100,
+                true
+            ),
+            EndorsementClass::Outdated
+        );
+    }
+
+    #[test]
+    fn at_head_on_applied_branch_is_applied() {
+        assert_eq!(
+            classify_endorsement_level(100, 100, true),
+            EndorsementClass::Applied
+        );
+    }
+
+    fn test_key(byte: u8) -> Key {
+        OperationHash::try_from(vec![byte; 32]).unwrap()
+    }
+
+    fn op(state: PrecheckerOperationState) -> PrecheckerOperation {
+        PrecheckerOperation {
+            state,
+            operation: None,
+            decoded_contents: None,
+            manager_account: None,
+        }
+    }
+
+    #[test]
+    fn reindex_moves_key_into_the_matching_class_only() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(1);
+        state.operations.insert(
+            key.clone(),
+            op(PrecheckerOperationState::Applied {
+                protocol_data: serde_json::json!({}),
+            }),
+        );
+
+        state.reindex_operation_class(&key);
+
+        assert!(state.applied.contains(&key));
+        assert!(!state.branch_delayed.contains(&key));
+        assert!(!state.refused.contains(&key));
+    }
+
+    #[test]
+    fn reindex_moves_key_out_of_its_old_class_when_state_changes() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(2);
+        state.operations.insert(
+            key.clone(),
+            op(PrecheckerOperationState::Refused {
+                protocol_data: serde_json::json!({}),
+                error: "bad signature".to_string(),
+            }),
+        );
+        state.reindex_operation_class(&key);
+        assert!(state.refused.contains(&key));
+
+        state.operations.get_mut(&key).unwrap().state = PrecheckerOperationState::Outdated {
+            protocol_data: serde_json::json!({}),
+            error: "bad signature".to_string(),
+        };
+        state.reindex_operation_class(&key);
+
+        assert!(!state.refused.contains(&key));
+        assert!(state.outdated.contains(&key));
+    }
+
+    #[test]
+    fn reindex_clears_a_key_no_longer_tracked() {
+        let mut state = PrecheckerState::default();
+        let key = test_key(3);
+        state.operations.insert(
+            key.clone(),
+            op(PrecheckerOperationState::Applied {
+                protocol_data: serde_json::json!({}),
+            }),
+        );
+        state.reindex_operation_class(&key);
+        assert!(state.applied.contains(&key));
+
+        state.operations.remove(&key);
+        state.reindex_operation_class(&key);
+
+        assert!(!state.applied.contains(&key));
+    }
+}
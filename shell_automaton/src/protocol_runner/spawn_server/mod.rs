@@ -0,0 +1,10 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+mod protocol_runner_spawn_server_actions;
+mod protocol_runner_spawn_server_reducer;
+mod protocol_runner_spawn_server_state;
+
+pub use protocol_runner_spawn_server_actions::*;
+pub use protocol_runner_spawn_server_reducer::*;
+pub use protocol_runner_spawn_server_state::*;
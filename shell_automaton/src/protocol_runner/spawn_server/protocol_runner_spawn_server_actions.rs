@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::protocol_runner::ProtocolRunnerState;
 use crate::{EnablingCondition, State};
 
-use super::ProtocolRunnerSpawnServerState;
+use super::{ProtocolRunnerSpawnServerState, MAX_SPAWN_ATTEMPTS};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProtocolRunnerSpawnServerInitAction {}
@@ -15,6 +15,12 @@ impl EnablingCondition<State> for ProtocolRunnerSpawnServerInitAction {
     fn is_enabled(&self, state: &State) -> bool {
         match &state.protocol_runner {
             ProtocolRunnerState::Idle => true,
+            // Only once the backoff delay computed by ProtocolRunnerSpawnServerRetryAction
+            // has actually elapsed - this must not fire the moment Retrying is entered.
+            ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Retrying {
+                next_retry_at,
+                ..
+            }) => state.time_as_nanos() >= *next_retry_at,
             _ => false,
         }
     }
@@ -38,7 +44,9 @@ pub struct ProtocolRunnerSpawnServerErrorAction {}
 impl EnablingCondition<State> for ProtocolRunnerSpawnServerErrorAction {
     fn is_enabled(&self, state: &State) -> bool {
         match &state.protocol_runner {
-            ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Pending {}) => true,
+            ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Pending { .. }) => {
+                true
+            }
             _ => false,
         }
     }
@@ -50,7 +58,29 @@ pub struct ProtocolRunnerSpawnServerSuccessAction {}
 impl EnablingCondition<State> for ProtocolRunnerSpawnServerSuccessAction {
     fn is_enabled(&self, state: &State) -> bool {
         match &state.protocol_runner {
-            ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Pending {}) => true,
+            ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Pending { .. }) => {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Schedules another spawn attempt after a transient failure, mirroring the
+/// auto-reconnect pattern used for IPC-backed subprocess clients. Enabled only while
+/// the spawn server is wedged in `Error` and the attempt budget isn't exhausted yet;
+/// its reducer computes `next_retry_at` from `base_delay * 2^attempt` (capped) and
+/// moves to `Retrying`, which `ProtocolRunnerSpawnServerInitAction` re-enters from once
+/// that delay elapses.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProtocolRunnerSpawnServerRetryAction {}
+
+impl EnablingCondition<State> for ProtocolRunnerSpawnServerRetryAction {
+    fn is_enabled(&self, state: &State) -> bool {
+        match &state.protocol_runner {
+            ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Error {
+                attempt,
+            }) => *attempt + 1 < MAX_SPAWN_ATTEMPTS,
             _ => false,
         }
     }
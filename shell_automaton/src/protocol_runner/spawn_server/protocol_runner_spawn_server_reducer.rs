@@ -0,0 +1,80 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use crate::protocol_runner::ProtocolRunnerState;
+use crate::State;
+
+use super::{
+    backoff_delay_ms, ProtocolRunnerSpawnServerErrorAction, ProtocolRunnerSpawnServerInitAction,
+    ProtocolRunnerSpawnServerPendingAction, ProtocolRunnerSpawnServerRetryAction,
+    ProtocolRunnerSpawnServerState, ProtocolRunnerSpawnServerSuccessAction,
+};
+
+fn attempt_in_progress(state: &State) -> u8 {
+    match &state.protocol_runner {
+        ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Retrying {
+            attempt,
+            ..
+        }) => *attempt,
+        _ => 0,
+    }
+}
+
+pub fn protocol_runner_spawn_server_reduce_init(
+    state: &mut State,
+    _action: &ProtocolRunnerSpawnServerInitAction,
+) {
+    state.protocol_runner = ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Init);
+}
+
+pub fn protocol_runner_spawn_server_reduce_pending(
+    state: &mut State,
+    _action: &ProtocolRunnerSpawnServerPendingAction,
+) {
+    let attempt = attempt_in_progress(state);
+    state.protocol_runner =
+        ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Pending { attempt });
+}
+
+pub fn protocol_runner_spawn_server_reduce_success(
+    state: &mut State,
+    _action: &ProtocolRunnerSpawnServerSuccessAction,
+) {
+    // Reaching Success resets the attempt counter: the next failure starts the backoff
+    // over again from attempt 0.
+    state.protocol_runner =
+        ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Success {});
+}
+
+pub fn protocol_runner_spawn_server_reduce_error(
+    state: &mut State,
+    _action: &ProtocolRunnerSpawnServerErrorAction,
+) {
+    let attempt = match &state.protocol_runner {
+        ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Pending { attempt }) => {
+            *attempt
+        }
+        _ => 0,
+    };
+    state.protocol_runner =
+        ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Error { attempt });
+}
+
+pub fn protocol_runner_spawn_server_reduce_retry(
+    state: &mut State,
+    _action: &ProtocolRunnerSpawnServerRetryAction,
+) {
+    let attempt = match &state.protocol_runner {
+        ProtocolRunnerState::SpawnServer(ProtocolRunnerSpawnServerState::Error { attempt }) => {
+            *attempt
+        }
+        _ => return,
+    };
+    let next_retry_at = state.time_as_nanos() + backoff_delay_ms(attempt) * 1_000_000;
+    state.protocol_runner = ProtocolRunnerState::SpawnServer(
+        ProtocolRunnerSpawnServerState::Retrying {
+            attempt: attempt + 1,
+            next_retry_at,
+        },
+    );
+}
@@ -0,0 +1,55 @@
+// Copyright (c) SimpleStaking, Viable Systems and Tezedge Contributors
+// SPDX-License-Identifier: MIT
+
+use serde::{Deserialize, Serialize};
+
+/// Base delay for the first retry. Doubled on every subsequent attempt and capped at
+/// `MAX_RETRY_DELAY_MS`.
+pub const BASE_RETRY_DELAY_MS: u64 = 250;
+/// Ceiling on the exponential backoff, so a long string of failures doesn't push the
+/// retry interval out indefinitely.
+pub const MAX_RETRY_DELAY_MS: u64 = 30_000;
+/// Number of consecutive failures tolerated before the spawn server gives up and stays
+/// in `Error` for good.
+pub const MAX_SPAWN_ATTEMPTS: u8 = 8;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ProtocolRunnerSpawnServerState {
+    Init,
+    Pending {
+        attempt: u8,
+    },
+    Success {},
+    Error {
+        attempt: u8,
+    },
+    /// Waiting out the backoff delay before the next spawn attempt.
+    Retrying {
+        attempt: u8,
+        next_retry_at: u64,
+    },
+}
+
+/// `base_delay * 2^attempt`, capped at `MAX_RETRY_DELAY_MS`.
+pub fn backoff_delay_ms(attempt: u8) -> u64 {
+    BASE_RETRY_DELAY_MS
+        .saturating_mul(1u64 << attempt.min(20))
+        .min(MAX_RETRY_DELAY_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_each_attempt() {
+        assert_eq!(backoff_delay_ms(0), BASE_RETRY_DELAY_MS);
+        assert_eq!(backoff_delay_ms(1), BASE_RETRY_DELAY_MS * 2);
+        assert_eq!(backoff_delay_ms(2), BASE_RETRY_DELAY_MS * 4);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(backoff_delay_ms(63), MAX_RETRY_DELAY_MS);
+    }
+}